@@ -2,18 +2,37 @@ use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 use eframe::{App, CreationContext, Frame, Storage};
-use egui::{CentralPanel, Context, DragValue, TextEdit, ThemePreference, Ui};
+use egui::{CentralPanel, Context, DragValue, Response, TextEdit, ThemePreference, Ui};
 use egui_extras::{Column, TableBuilder};
-use number_prefix::{NumberPrefix, Prefix};
+use egui_plot::{HLine, Line, Plot, PlotPoint, Text, VLine};
+use indexmap::IndexMap;
+use number_prefix::NumberPrefix;
 use serde::{Deserialize, Serialize};
-use crate::calc;
+use serde::de::{IgnoredAny, MapAccess, Visitor};
+use serde::de::value::MapAccessDeserializer;
+use link_budget_calculator::{calc, parse_prefixed, LinkBudget, Target};
+use link_budget_calculator::budget::{
+    ApertureAntenna, CableType, Condition, Fading, FrequencyScaling, Gain, Hop, Loss, LossCondition, NoiseStage,
+    PolarizationMismatch, PropagationModel, RatioUnit, TermKind, FADING_MARGIN_NAME, POLARIZATION_LOSS_NAME,
+};
+use link_budget_calculator::calc::feeder::FeederType;
+use link_budget_calculator::calc::rain::Polarization;
+use crate::{asynch, export};
 
+/// Compact only shows the core of the budget, Advanced adds the detailed panels
 #[derive(Default, PartialEq, Eq, Serialize, Deserialize)]
-enum CalculationTarget {
+enum View {
     #[default]
-    Snr,
-    Distance,
-    TxPower,
+    Compact,
+    Advanced,
+}
+
+/// Part of the budget that Reset restores to the defaults
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ResetScope {
+    Everything,
+    Parameters, // keeps the gains, losses and antennas
+    Tables,     // only clears the gains, losses and antennas
 }
 
 #[derive(Default, Clone, Serialize, Deserialize, Eq, PartialEq)]
@@ -23,6 +42,7 @@ enum PowerUnit {
     DbWatt,
     Milliwatt,
     Watt,
+    DbMicrovolt,
 }
 
 impl Display for PowerUnit {
@@ -32,11 +52,183 @@ impl Display for PowerUnit {
             PowerUnit::DbWatt => { write!(f, "dBW") }
             PowerUnit::Milliwatt => { write!(f, "mW") }
             PowerUnit::Watt => { write!(f, "W") }
+            PowerUnit::DbMicrovolt => { write!(f, "dBµV") }
+        }
+    }
+}
+
+/// A frequency band with its regulatory EIRP limit
+struct BandPreset {
+    frequency: f64,   // Hertz, center of the preset channel
+    bandwidth: f64,   // Hertz
+    band: (f64, f64), // Hertz, the range the limit applies to
+    max_eirp: f64,    // dBm
+}
+
+const BAND_PRESETS: &[(&str, BandPreset)] = &[
+    ("WiFi 2.4 GHz (EU)", BandPreset { frequency: 2.437e9, bandwidth: 20e6, band: (2.4e9, 2.4835e9), max_eirp: 20.0 }),
+    ("WiFi 2.4 GHz (US)", BandPreset { frequency: 2.437e9, bandwidth: 20e6, band: (2.4e9, 2.4835e9), max_eirp: 36.0 }),
+    ("LoRa 868 MHz (EU)", BandPreset { frequency: 868.1e6, bandwidth: 125e3, band: (868e6, 868.6e6), max_eirp: 16.15 }),
+    ("LoRa 915 MHz (US)", BandPreset { frequency: 915e6, bandwidth: 125e3, band: (902e6, 928e6), max_eirp: 36.0 }),
+    ("ISM 5.8 GHz (EU)", BandPreset { frequency: 5.8e9, bandwidth: 20e6, band: (5.725e9, 5.875e9), max_eirp: 14.0 }),
+    ("ISM 5.8 GHz (US)", BandPreset { frequency: 5.8e9, bandwidth: 20e6, band: (5.725e9, 5.85e9), max_eirp: 36.0 }),
+];
+
+/// Link margin in dB above which the status bar shows the link as comfortably closed
+const COMFORTABLE_MARGIN: f64 = 3.0;
+
+/// Modulation with the Eb/N0 it needs for a bit error rate of 1e-6 without coding
+#[derive(Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Modulation {
+    #[default]
+    Custom,
+    Bpsk,
+    Qpsk,
+    Qam16,
+}
+
+impl Display for Modulation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Modulation::Custom => { write!(f, "Custom") }
+            Modulation::Bpsk => { write!(f, "BPSK") }
+            Modulation::Qpsk => { write!(f, "QPSK") }
+            Modulation::Qam16 => { write!(f, "16-QAM") }
+        }
+    }
+}
+
+impl Modulation {
+    /// Eb/N0 in dB for a BER of 1e-6, None for a custom threshold
+    pub fn required_ebn0(&self) -> Option<f64> {
+        match self {
+            Modulation::Custom => { None }
+            Modulation::Bpsk => { Some(10.5) }
+            Modulation::Qpsk => { Some(10.5) }
+            Modulation::Qam16 => { Some(14.4) }
+        }
+    }
+}
+
+/// Value editor for a ratio in its unit, the unit is changed with a right click. A value failing
+/// the validation is highlighted in red and the reason is shown as tooltip.
+fn ratio_ui(ui: &mut Ui, value_db: &mut f64, unit: &mut RatioUnit, validate: fn(f64) -> Result<(), String>) {
+    let mut value = unit.db_to_unit(*value_db);
+    let mut drag_value = DragValue::new(&mut value).suffix(format!(" {unit}"));
+    if *unit == RatioUnit::Linear {
+        drag_value = drag_value.range(f64::MIN_POSITIVE..=f64::MAX).speed(0.01);
+    }
+    let validation = validate(*value_db);
+    let response = ui
+        .scope(|ui| {
+            if validation.is_err() {
+                let error = ui.visuals().error_fg_color;
+                let visuals = ui.visuals_mut();
+                visuals.override_text_color = Some(error);
+                for widget in [&mut visuals.widgets.inactive, &mut visuals.widgets.hovered, &mut visuals.widgets.active] {
+                    widget.bg_stroke = egui::Stroke::new(1.0, error);
+                }
+            }
+            ui.add(drag_value)
+        })
+        .inner;
+    if response.changed() {
+        *value_db = unit.unit_to_db(value);
+    }
+    let hover_text = match validation {
+        Ok(()) => { "Right click to change the unit".to_string() }
+        Err(e) => { format!("{e}\nRight click to change the unit") }
+    };
+    response
+        .on_hover_text(hover_text)
+        .context_menu(|ui| {
+            ui.selectable_value(unit, RatioUnit::Db, "dB");
+            ui.selectable_value(unit, RatioUnit::Linear, "linear");
+        });
+}
+
+/// Scaling selector of a table entry with the slope editor of the slope scalings, the reference
+/// frequency is set to the current frequency when the scaling is changed so that the value stays
+/// the same
+fn scaling_ui(
+    ui: &mut Ui,
+    id_salt: impl std::hash::Hash,
+    scaling: &mut FrequencyScaling,
+    reference_frequency: &mut f64,
+    slope: &mut f64,
+    frequency: f64,
+) {
+    let before = *scaling;
+    ui.horizontal(|ui| {
+        egui::ComboBox::new(id_salt, "").width(50.0)
+            .selected_text(scaling.to_string())
+            .show_ui(ui, |ui| {
+                for option in [
+                    FrequencyScaling::Flat,
+                    FrequencyScaling::SqrtF,
+                    FrequencyScaling::FSquared,
+                    FrequencyScaling::PerOctave,
+                    FrequencyScaling::PerGhz,
+                ] {
+                    ui.selectable_value(scaling, option, option.to_string());
+                }
+            });
+        if scaling.has_slope() {
+            ui.add(DragValue::new(slope).speed(0.01))
+                .on_hover_text(format!("Slope in {scaling} from {}", format_frequency(*reference_frequency)));
+        }
+    });
+    if *scaling != before {
+        *reference_frequency = frequency;
+    }
+}
+
+/// SI prefix a frequency is displayed with, `Auto` picks the prefix from the value
+#[derive(Default, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+enum SiPrefix {
+    #[default]
+    Auto,
+    Kilo,
+    Mega,
+    Giga,
+    Tera,
+}
+
+impl SiPrefix {
+    pub fn factor(&self) -> Option<f64> {
+        match self {
+            SiPrefix::Auto => { None }
+            SiPrefix::Kilo => { Some(1e3) }
+            SiPrefix::Mega => { Some(1e6) }
+            SiPrefix::Giga => { Some(1e9) }
+            SiPrefix::Tera => { Some(1e12) }
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            SiPrefix::Auto => { "" }
+            SiPrefix::Kilo => { "k" }
+            SiPrefix::Mega => { "M" }
+            SiPrefix::Giga => { "G" }
+            SiPrefix::Tera => { "T" }
         }
     }
+
+    pub fn selector_ui(&mut self, id_salt: &str, ui: &mut Ui) {
+        egui::ComboBox::new(id_salt, "").width(60.0)
+            .selected_text(format!("{}Hz", self.symbol()))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(self, SiPrefix::Auto, "auto");
+                ui.selectable_value(self, SiPrefix::Kilo, "kHz");
+                ui.selectable_value(self, SiPrefix::Mega, "MHz");
+                ui.selectable_value(self, SiPrefix::Giga, "GHz");
+                ui.selectable_value(self, SiPrefix::Tera, "THz");
+            });
+    }
 }
 
-#[derive(Default, Serialize, Deserialize)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 struct Power {
     pub val_dbm: f64,
     pub unit: PowerUnit,
@@ -50,10 +242,25 @@ impl Display for Power {
 
 impl Power {
 
-    pub fn value_selector_ui(&mut self, ui: &mut Ui) {
+    /// Value and unit editor in one widget
+    pub fn ui(&mut self, ui: &mut Ui, id_salt: &str) -> Response {
+        ui.horizontal(|ui| {
+            let mut response = self.value_selector_ui(ui);
+            let unit = self.unit.clone();
+            self.unit_selector_ui(id_salt, ui);
+            if self.unit != unit {
+                response.mark_changed();
+            }
+            response
+        })
+        .inner
+    }
+
+    pub fn value_selector_ui(&mut self, ui: &mut Ui) -> Response {
         let mut val_unit = self.get_in_unit();
-        ui.add(DragValue::new(&mut val_unit));
+        let response = ui.add(DragValue::new(&mut val_unit).speed(0.1));
         self.value_from_unit(val_unit);
+        response
     }
 
     pub fn unit_selector_ui(&mut self, id_salt: &str, ui: &mut Ui) {
@@ -64,6 +271,8 @@ impl Power {
                     ui.selectable_value(&mut self.unit, PowerUnit::DbWatt, "dBW");
                     ui.selectable_value(&mut self.unit, PowerUnit::Milliwatt, "mW");
                     ui.selectable_value(&mut self.unit, PowerUnit::Watt, "W");
+                    ui.selectable_value(&mut self.unit, PowerUnit::DbMicrovolt, "dBµV")
+                        .on_hover_text(format!("Voltage across {} Ω", calc::SYSTEM_IMPEDANCE));
                 });
     }
 
@@ -73,6 +282,7 @@ impl Power {
             PowerUnit::DbWatt => { calc::dbm_to_dbw(self.val_dbm) }
             PowerUnit::Milliwatt => { calc::dbm_to_milliwat(self.val_dbm) }
             PowerUnit::Watt => { calc::dbm_to_watt(self.val_dbm) }
+            PowerUnit::DbMicrovolt => { calc::dbm_to_dbuv(self.val_dbm, calc::SYSTEM_IMPEDANCE) }
         }
     }
 
@@ -82,331 +292,3080 @@ impl Power {
             PowerUnit::DbWatt => { calc::dbw_to_dbm(val_unit) }
             PowerUnit::Milliwatt => { calc::milliwatt_to_dbm(val_unit) }
             PowerUnit::Watt => { calc::watt_to_dbm(val_unit) }
+            PowerUnit::DbMicrovolt => { calc::dbuv_to_dbm(val_unit, calc::SYSTEM_IMPEDANCE) }
+        };
+    }
+}
+
+#[derive(Default, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+enum LengthUnit {
+    #[default]
+    Meter,
+    Kilometer,
+    Mile,
+    NauticalMile,
+}
+
+impl Display for LengthUnit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LengthUnit::Meter => { write!(f, "m") }
+            LengthUnit::Kilometer => { write!(f, "km") }
+            LengthUnit::Mile => { write!(f, "mi") }
+            LengthUnit::NauticalMile => { write!(f, "NM") }
+        }
+    }
+}
+
+impl LengthUnit {
+    /// Step of the +/- buttons
+    pub fn step(&self) -> f64 {
+        match self {
+            LengthUnit::Meter => { 1.0 }
+            LengthUnit::Kilometer | LengthUnit::Mile | LengthUnit::NauticalMile => { 0.01 }
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+enum TemperatureUnit {
+    #[default]
+    Kelvin,
+    Celsius,
+    Fahrenheit,
+}
+
+impl Display for TemperatureUnit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemperatureUnit::Kelvin => { write!(f, "K") }
+            TemperatureUnit::Celsius => { write!(f, "°C") }
+            TemperatureUnit::Fahrenheit => { write!(f, "°F") }
+        }
+    }
+}
+
+impl TemperatureUnit {
+    pub fn from_kelvin(&self, kelvin: f64) -> f64 {
+        match self {
+            TemperatureUnit::Kelvin => { kelvin }
+            TemperatureUnit::Celsius => { calc::kelvin_to_celsius(kelvin) }
+            TemperatureUnit::Fahrenheit => { calc::kelvin_to_fahrenheit(kelvin) }
+        }
+    }
+
+    pub fn to_kelvin(&self, value: f64) -> f64 {
+        match self {
+            TemperatureUnit::Kelvin => { value }
+            TemperatureUnit::Celsius => { calc::celsius_to_kelvin(value) }
+            TemperatureUnit::Fahrenheit => { calc::fahrenheit_to_kelvin(value) }
+        }
+    }
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+#[serde(remote = "Length")]
+struct Length {
+    pub val_m: f64,
+    pub unit: LengthUnit,
+}
+
+impl Serialize for Length {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Length::serialize(self, serializer)
+    }
+}
+
+/// States from before the length units stored a bare number in meter
+impl<'de> Deserialize<'de> for Length {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(LengthVisitor)
+    }
+}
+
+struct LengthVisitor;
+
+impl<'de> Visitor<'de> for LengthVisitor {
+    type Value = Length;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "a length in meter or a length with its unit")
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, val_m: f64) -> Result<Length, E> {
+        Ok(Length::meters(val_m))
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, val_m: i64) -> Result<Length, E> {
+        Ok(Length::meters(val_m as f64))
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, val_m: u64) -> Result<Length, E> {
+        Ok(Length::meters(val_m as f64))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Length, A::Error> {
+        Length::deserialize(MapAccessDeserializer::new(map))
+    }
+}
+
+impl Length {
+    pub fn meters(val_m: f64) -> Self {
+        Self {
+            val_m,
+            unit: LengthUnit::Meter,
+        }
+    }
+
+    pub fn value_selector_ui(&mut self, ui: &mut Ui, step_scale: f64) -> Response {
+        let mut val_unit = self.get_in_unit();
+        let response = stepper(ui, &mut val_unit, self.unit.step() * step_scale, "");
+        if response.changed() {
+            self.value_from_unit(val_unit);
+        }
+        response
+    }
+
+    pub fn unit_selector_ui(&mut self, id_salt: &str, ui: &mut Ui) {
+        egui::ComboBox::new(id_salt, "").width(50.0)
+            .selected_text(self.unit.to_string())
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.unit, LengthUnit::Meter, "m");
+                ui.selectable_value(&mut self.unit, LengthUnit::Kilometer, "km");
+                ui.selectable_value(&mut self.unit, LengthUnit::Mile, "mi");
+                ui.selectable_value(&mut self.unit, LengthUnit::NauticalMile, "NM");
+            });
+    }
+
+    pub fn get_in_unit(&self) -> f64 {
+        match self.unit {
+            LengthUnit::Meter => { self.val_m }
+            LengthUnit::Kilometer => { calc::meters_to_kilometers(self.val_m) }
+            LengthUnit::Mile => { calc::meters_to_miles(self.val_m) }
+            LengthUnit::NauticalMile => { calc::meters_to_nautical_miles(self.val_m) }
+        }
+    }
+
+    pub fn value_from_unit(&mut self, val_unit: f64) {
+        self.val_m = match self.unit {
+            LengthUnit::Meter => { val_unit }
+            LengthUnit::Kilometer => { calc::kilometers_to_meters(val_unit) }
+            LengthUnit::Mile => { calc::miles_to_meters(val_unit) }
+            LengthUnit::NauticalMile => { calc::nautical_miles_to_meters(val_unit) }
         };
     }
 }
 
+fn default_cable_types() -> Vec<CableType> {
+    FeederType::ALL.into_iter().map(CableType::feeder).collect()
+}
+
 #[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct LinkBudgetApp {
-    temperature: f64,  // Kelvin
-    frequency: f64,    // Hertz
-    bandwidth: f64,    // Hertz
-    snr: f64,      // dB
+    budget: LinkBudget, // the inputs in SI units, solved for the calculation target every frame
 
-    tx_power: Power,
+    temperature_unit: TemperatureUnit,
+    frequency_prefix: SiPrefix,
+    bandwidth_prefix: SiPrefix,
+    modulation: Modulation,
+
+    tx_power_unit: PowerUnit,
     rx_power: Power,
+    eirp: Power,
 
-    distance: f64, // meter
-    d_break: f64,  // meter
-    break_exponent: f64,
+    distance_unit: LengthUnit,
+    d_break_unit: LengthUnit,
 
-    losses: HashMap<String, f64>,
     loss_name: String,
+    loss_value: f64, // dB, value of the next added loss
+    loss_category: String,
 
-    gains: HashMap<String, f64>,
     gain_name: String,
+    gain_value: f64, // dB, value of the next added gain
+    gain_category: String,
+    dish_diameter: f64,   // meter
+    beamwidth_azimuth: f64,   // degree, half power
+    beamwidth_elevation: f64, // degree, half power
+    dish_efficiency: f64, // 0..1
+
+    antenna_name: String,
+
+    cable_types: Vec<CableType>,
+    cable_selected: usize,
+    cable_length: f64, // meter
+    show_cable_loss: bool,
+    show_cable_types: bool,
+
+    checkpoint_distances: String, // comma separated, meter
+
+    sweep_f_start: f64, // Hertz
+    sweep_f_stop: f64,  // Hertz
+    sweep_steps: usize,
+    sweep_d_start: f64, // meter
+    sweep_d_stop: f64,  // meter
 
-    calculation_target: CalculationTarget,
+    monte_carlo_samples: usize,
+    #[serde(skip)]
+    monte_carlo_margins: Vec<f64>, // dB, result of the last run
+    #[serde(skip)]
+    monte_carlo_job: Option<asynch::Background<Vec<f64>>>,
+
+    show_wizard: bool,
+    wizard_range: f64,     // meter
+    wizard_data_rate: f64, // bit/s
+    wizard_margin: f64,    // dB
+
+    view: View,
+    step_scale: f64, // multiplier of the per field step sizes
+    precision: usize, // decimals of the displayed dB values
+    path_loss_as_gain: bool, // show the path loss negative
+    theme: ThemePreference,
+
+    band_preset: String, // name of the selected preset, empty for none
+    scenarios: HashMap<String, LinkBudget>,
+    scenario_name: String,
+    show_comparison: bool,
+    compare_a: String, // scenario name, empty for the current budget
+    compare_b: String,
+
+    #[serde(skip)]
+    import_error: Option<String>,
+    #[serde(skip)]
+    show_reset: bool,
+    #[serde(skip)]
+    screenshot_pending: bool, // Export PNG waits for the screenshot of the next frame
+    #[serde(skip)]
+    history: History,
+    log: Vec<LogEntry>,
+    #[serde(skip)]
+    logged_target: Option<Target>, // target of the last frame, None before the first
 }
 
-impl Default for LinkBudgetApp {
+/// Number of states undo can go back
+const HISTORY_LENGTH: usize = 100;
+
+/// Undo and redo stacks of serialized snapshots
+#[derive(Default)]
+struct History {
+    undo: Vec<String>,
+    redo: Vec<String>,
+    current: Option<String>,
+}
+
+impl History {
+    /// Record the state if it differs from the last recorded one
+    fn record(&mut self, state: String) {
+        if self.current.as_ref() == Some(&state) {
+            return;
+        }
+        if let Some(previous) = self.current.replace(state) {
+            self.undo.push(previous);
+            if self.undo.len() > HISTORY_LENGTH {
+                self.undo.remove(0);
+            }
+            self.redo.clear();
+        }
+    }
+
+    /// State to restore for undo
+    fn undo(&mut self) -> Option<String> {
+        let state = self.undo.pop()?;
+        self.redo.extend(self.current.replace(state.clone()));
+        Some(state)
+    }
+
+    /// State to restore for redo
+    fn redo(&mut self) -> Option<String> {
+        let state = self.redo.pop()?;
+        self.undo.extend(self.current.replace(state.clone()));
+        Some(state)
+    }
+}
+
+/// A solve result in the log, recorded when the calculation target changes or on request
+#[derive(Clone, Serialize, Deserialize)]
+struct LogEntry {
+    timestamp: u64, // seconds since the Unix epoch
+    event: String,
+    target: Target,
+    frequency: f64, // Hertz
+    distance: f64,  // meter
+    tx_power: f64,  // dBm
+    snr: f64,       // dB
+    margin: f64,    // dB
+}
+
+/// Fields of the states stored before the budget was a field of its own. The values of the budget
+/// were fields of the app next to them, they are read as a [`LinkBudget`] from the same state.
+#[derive(Deserialize)]
+#[serde(default)]
+struct FlatState {
+    budget: Option<IgnoredAny>, // only in the states that are not flat
+    tx_power: Power,
+    distance: Length,
+    d_break: Length,
+    constants: calc::Constants,
+    refractive_index: f64,
+}
+
+impl Default for FlatState {
     fn default() -> Self {
         Self {
-            temperature: 290.0,
-            bandwidth: 20e6,
-            snr: 10.0,
-            frequency: 2.4e9,
+            budget: None,
             tx_power: Power::default(),
+            distance: Length::default(),
+            d_break: Length::default(),
+            constants: calc::Constants::default(),
+            refractive_index: calc::Physics::default().refractive_index,
+        }
+    }
+}
+
+/// Version of the JSON file format, increase when a change breaks reading older files
+const STATE_FILE_VERSION: u32 = 1;
+
+/// The app state as it is written to a JSON file
+#[derive(Serialize, Deserialize)]
+struct StateFile<T> {
+    version: u32,
+    state: T,
+}
+
+impl Default for LinkBudgetApp {
+    fn default() -> Self {
+        Self {
+            budget: LinkBudget::default(),
+            temperature_unit: TemperatureUnit::default(),
+            modulation: Modulation::Qpsk,
+            frequency_prefix: SiPrefix::default(),
+            bandwidth_prefix: SiPrefix::default(),
+            tx_power_unit: PowerUnit::default(),
             rx_power: Power::default(),
-            distance: 2000.0,
-            d_break: 500.0,
-            break_exponent: 4.3,
-            losses: HashMap::default(),
+            eirp: Power::default(),
+            distance_unit: LengthUnit::default(),
+            d_break_unit: LengthUnit::default(),
             loss_name: String::default(),
-            gains: HashMap::new(),
+            loss_value: 10.0,
+            loss_category: String::new(),
             gain_name: String::new(),
-            calculation_target: CalculationTarget::default(),
+            gain_value: 10.0,
+            gain_category: String::new(),
+            dish_diameter: 1.0,
+            beamwidth_azimuth: 30.0,
+            beamwidth_elevation: 30.0,
+            dish_efficiency: calc::antenna::DEFAULT_EFFICIENCY,
+            antenna_name: String::new(),
+            cable_types: default_cable_types(),
+            cable_selected: 0,
+            cable_length: 1.0,
+            show_cable_loss: false,
+            show_cable_types: false,
+            checkpoint_distances: "100, 500, 1k, 5k".to_string(),
+            sweep_f_start: 100e6,
+            sweep_f_stop: 10e9,
+            sweep_steps: 200,
+            sweep_d_start: 10.0,
+            sweep_d_stop: 100e3,
+            monte_carlo_samples: 10_000,
+            monte_carlo_margins: Vec::new(),
+            monte_carlo_job: None,
+            show_wizard: false,
+            wizard_range: 10e3,
+            wizard_data_rate: 10e6,
+            wizard_margin: 3.0,
+            view: View::default(),
+            step_scale: 1.0,
+            precision: 2,
+            path_loss_as_gain: false,
+            theme: ThemePreference::System,
+            band_preset: String::new(),
+            scenarios: HashMap::new(),
+            scenario_name: String::new(),
+            show_comparison: false,
+            compare_a: String::new(),
+            compare_b: String::new(),
+            import_error: None,
+            show_reset: false,
+            screenshot_pending: false,
+            history: History::default(),
+            log: Vec::new(),
+            logged_target: None,
         }
     }
 }
 impl LinkBudgetApp {
     pub fn new(cc: &CreationContext) -> Result<Box<dyn App>, Box<dyn Error + Send + Sync>> {
         if let Some(storage) = cc.storage {
-            return Ok(Box::new(eframe::get_value::<LinkBudgetApp>(storage, eframe::APP_KEY).unwrap_or_default()));
+            let mut app = eframe::get_value::<LinkBudgetApp>(storage, eframe::APP_KEY).unwrap_or_default();
+            let flat = eframe::get_value(storage, eframe::APP_KEY);
+            if let (Some(flat), Some(budget)) = (flat, eframe::get_value(storage, eframe::APP_KEY)) {
+                app.migrate(flat, budget);
+            }
+            app.apply_theme(&cc.egui_ctx);
+            return Ok(Box::new(app));
         }
         Ok(Box::new(Self::default()))
     }
 
-    pub fn total_losses(&self) -> f64 {
-        self.losses.iter().map(|(_, l)| *l).sum()
+    /// Restore the theme of the last session, eframe restores the window itself
+    fn apply_theme(&self, ctx: &Context) {
+        ctx.set_theme(self.theme);
+    }
+
+    /// Remember the theme for the next session
+    fn store_theme(&mut self, ctx: &Context) {
+        self.theme = ctx.options(|options| options.theme_preference);
+    }
+
+    /// Replace the budget with one of the history or a scenario, the modulation is kept while it
+    /// gives the required Eb/N0 of the budget
+    fn restore(&mut self, budget: LinkBudget) {
+        if self.modulation.required_ebn0() != Some(budget.required_ebn0) {
+            self.modulation = Modulation::Custom;
+        }
+        self.budget = budget;
+    }
+
+    /// Take over the budget of a state stored before the budget was a field of its own, the
+    /// budget is read from the same state
+    fn migrate(&mut self, flat: FlatState, budget: LinkBudget) {
+        if flat.budget.is_some() {
+            return;
+        }
+        self.budget = budget;
+        self.budget.physics = calc::Physics {
+            constants: flat.constants,
+            refractive_index: flat.refractive_index,
+        };
+        self.tx_power_unit = flat.tx_power.unit;
+        self.distance_unit = flat.distance.unit;
+        self.d_break_unit = flat.d_break.unit;
     }
 
-    pub fn total_gains(&self) -> f64 {
-        self.gains.iter().map(|(_, g)| *g).sum()
+    fn ui_scenarios_menu(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.add(TextEdit::singleline(&mut self.scenario_name).hint_text("Name").desired_width(120.0));
+            let name = self.scenario_name.trim().to_string();
+            if ui.add_enabled(!name.is_empty(), egui::Button::new("Save scenario")).clicked() {
+                self.scenarios.insert(name, self.budget.clone());
+                ui.close_menu();
+            }
+        });
+        if self.scenarios.is_empty() {
+            ui.label("No saved scenarios");
+            return;
+        }
+        if ui.button("Compare…").clicked() {
+            self.show_comparison = true;
+            ui.close_menu();
+        }
+        ui.separator();
+        let mut names: Vec<String> = self.scenarios.keys().cloned().collect();
+        names.sort();
+        for name in names {
+            ui.horizontal(|ui| {
+                if ui.button(format!("Load scenario \"{name}\"")).clicked() {
+                    if let Some(snapshot) = self.scenarios.get(&name) {
+                        self.restore(snapshot.clone());
+                    }
+                    self.scenario_name = name.clone();
+                    ui.close_menu();
+                }
+                if ui.small_button("×").on_hover_text("Delete scenario").clicked() {
+                    self.scenarios.remove(&name);
+                }
+            });
+        }
     }
 
+    /// Parameters and results of a budget next to the ones of the other budget, as name, own
+    /// value and other value. A gain, aperture antenna or loss is NaN in the budget that does not
+    /// have it and zero where it does not count.
+    pub fn diff(budget: &LinkBudget, other: &LinkBudget) -> Vec<(String, f64, f64)> {
+        let quantities: [(&str, fn(&LinkBudget) -> f64); 16] = [
+            ("frequency [MHz]", |budget| budget.frequency / 1e6),
+            ("bandwidth [MHz]", |budget| budget.bandwidth / 1e6),
+            ("temperature [K]", |budget| budget.temperature),
+            ("noise figure [dB]", |budget| budget.noise_figure),
+            ("Tx power [dBm]", |budget| budget.tx_power),
+            ("EIRP [dBm]", |budget| budget.eirp()),
+            ("distance [m]", |budget| budget.distance),
+            ("path loss [dB]", |budget| budget.path_loss()),
+            ("excess loss [dB]", |budget| budget.excess_loss()),
+            ("gains [dB]", |budget| budget.total_gains()),
+            ("losses [dB]", |budget| budget.total_losses()),
+            ("noise floor [dBm]", |budget| budget.noise_floor()),
+            ("SNR [dB]", |budget| budget.snr),
+            ("required SNR [dB]", |budget| budget.required_snr),
+            ("implementation margin [dB]", |budget| budget.implementation_margin),
+            ("margin [dB]", |budget| budget.link_margin()),
+        ];
+        let mut rows: Vec<(String, f64, f64)> = quantities
+            .iter()
+            .map(|(name, value)| (name.to_string(), value(budget), value(other)))
+            .collect();
 
-    pub fn total_sum(&self) -> f64 {
-        let thermal =
-            calc::watt_to_dbm(calc::thermal_noise_power(self.temperature, self.bandwidth));
-        let losses = self.total_losses();
-        let gains = self.total_gains();
-        let path = calc::friis::path_loss(self.distance, self.d_break, self.frequency, self.break_exponent);
+        let gain = |budget: &LinkBudget, name: &str| {
+            budget.gains.get(name).map_or(f64::NAN, |g| if g.enabled { g.value_at(budget.frequency) } else { 0.0 })
+        };
+        let mut gain_names: Vec<&String> = budget.gains.keys().collect();
+        gain_names.extend(other.gains.keys().filter(|name| !budget.gains.contains_key(*name)));
+        rows.extend(gain_names.into_iter().map(|name| (format!("{name} [dB]"), gain(budget, name), gain(other, name))));
 
-        let negative =
-            thermal
-                + losses
-                + path
-                + self.snr;
-        let positive = self.tx_power.val_dbm + gains;
+        let antenna = |budget: &LinkBudget, name: &str| {
+            budget.antennas.get(name).map_or(f64::NAN, |a| a.gain(budget.frequency, budget.physics))
+        };
+        let mut antenna_names: Vec<&String> = budget.antennas.keys().collect();
+        antenna_names.extend(other.antennas.keys().filter(|name| !budget.antennas.contains_key(*name)));
+        rows.extend(
+            antenna_names.into_iter().map(|name| (format!("{name} [dBi]"), antenna(budget, name), antenna(other, name))),
+        );
 
-        return positive - negative;
+        let loss = |budget: &LinkBudget, name: &str| {
+            budget.losses
+                .get(name)
+                .map_or(f64::NAN, |l| if l.applies(budget.condition) { l.value_at(budget.frequency) } else { 0.0 })
+        };
+        let mut loss_names: Vec<&String> = budget.losses.keys().collect();
+        loss_names.extend(other.losses.keys().filter(|name| !budget.losses.contains_key(*name)));
+        rows.extend(loss_names.into_iter().map(|name| (format!("{name} [dB]"), -loss(budget, name), -loss(other, name))));
+        rows
     }
 
-    fn ui_parameters(&mut self, ui: &mut Ui) {
-        frame_styled(ui).show(ui, |ui| {
-            ui.vertical(|ui| {
-                ui.heading("Parameters");
-                egui::Grid::new("base_data").num_columns(3).show(ui, |ui| {
-                    ui.label("Temperature");
-                    ui.add(DragValue::new(&mut self.temperature));
-                    ui.label("K");
-                    ui.end_row();
+    fn ui_comparison_window(&mut self, ctx: &Context) {
+        let precision = self.precision;
+        let mut open = self.show_comparison;
+        let mut names: Vec<String> = self.scenarios.keys().cloned().collect();
+        names.sort();
+        let label = |name: &str| if name.is_empty() { "Current budget".to_string() } else { name.to_string() };
+        egui::Window::new("Compare scenarios")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for (id_salt, selected) in [("compare_a", &mut self.compare_a), ("compare_b", &mut self.compare_b)] {
+                        egui::ComboBox::new(id_salt, "")
+                            .selected_text(label(selected.as_str()))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(selected, String::new(), label(""));
+                                for name in &names {
+                                    ui.selectable_value(selected, name.clone(), name.as_str());
+                                }
+                            });
+                    }
+                });
+                // a deleted scenario falls back to the current budget
+                let budget = |name: &str| self.scenarios.get(name).unwrap_or(&self.budget);
+                let rows = Self::diff(budget(&self.compare_a), budget(&self.compare_b));
+                let format = |value: f64| if value.is_nan() { "–".to_string() } else { format!("{value:.precision$}") };
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    egui::Grid::new("comparison").num_columns(4).striped(true).show(ui, |ui| {
+                        ui.strong("");
+                        ui.strong(label(&self.compare_a));
+                        ui.strong(label(&self.compare_b));
+                        ui.strong("Δ");
+                        ui.end_row();
+                        for (name, value_a, value_b) in rows {
+                            ui.label(name);
+                            ui.label(format(value_a));
+                            ui.label(format(value_b));
+                            let delta = value_b - value_a;
+                            // a delta that rounds to zero at the displayed precision is left out
+                            if delta.abs() >= 0.5 * 10f64.powi(-(precision as i32)) {
+                                ui.colored_label(ui.visuals().warn_fg_color, format!("{delta:+.precision$}"));
+                            } else if delta.is_nan() && !(value_a.is_nan() && value_b.is_nan()) {
+                                ui.colored_label(ui.visuals().warn_fg_color, "only in one");
+                            } else {
+                                ui.label("");
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        self.show_comparison = open;
+    }
 
-                    ui.label("Bandwidth");
-                    ui.add(
-                        prefix_drag_value(&mut self.bandwidth)
-                            .range(0.0..=f64::MAX)
-                            .speed(1e6),
-                    );
-                    ui.label("Hz");
+
+    fn ui_atmosphere(&mut self, ui: &mut Ui) {
+        let precision = self.precision;
+        ui.collapsing("Atmosphere and rain", |ui| {
+            ui.checkbox(&mut self.budget.atmosphere, "Gas absorption (ITU-R P.676)");
+            ui.add_enabled_ui(self.budget.atmosphere, |ui| {
+                egui::Grid::new("atmosphere").num_columns(3).show(ui, |ui| {
+                    ui.label("Air temperature");
+                    ui.add(DragValue::new(&mut self.budget.air_temperature).range(0.0..=f64::MAX));
+                    ui.label("K");
                     ui.end_row();
 
-                    let thermal_noise_floor = calc::watt_to_dbm(calc::thermal_noise_power(self.temperature, self.bandwidth));
-                    ui.label("Thermal noise floor");
-                    ui.label(format!("{thermal_noise_floor:.1}"));
-                    ui.label("dBm");
+                    ui.label("Pressure");
+                    ui.add(DragValue::new(&mut self.budget.pressure).range(0.0..=f64::MAX));
+                    ui.label("hPa");
                     ui.end_row();
 
-                    ui.label("Frequency");
-                    ui.add(
-                        prefix_drag_value(&mut self.frequency)
-                            .range(0.0..=f64::MAX)
-                            .speed(1e6),
-                    );
-                    ui.label("Hz");
+                    ui.label("Water vapour density");
+                    ui.add(DragValue::new(&mut self.budget.water_vapor_density).range(0.0..=f64::MAX).speed(0.1));
+                    ui.label("g/m³");
                     ui.end_row();
 
-                    ui.selectable_value(
-                        &mut self.calculation_target,
-                        CalculationTarget::Snr,
-                        "SNR",
-                    );
-                    ui.add(DragValue::new(&mut self.snr));
+                    ui.label("Gas loss");
+                    ui.label(format!("{:.precision$}", self.budget.atmospheric_loss()));
                     ui.label("dB");
                     ui.end_row();
+                });
+            });
 
-                    ui.selectable_value(
-                        &mut self.calculation_target,
-                        CalculationTarget::TxPower,
-                        "Tx Power",
-                    );
-                    self.tx_power.value_selector_ui( ui);
-                    self.tx_power.unit_selector_ui("tx_power", ui);
-                    ui.end_row();
+            ui.separator();
+            egui::Grid::new("rain").num_columns(3).show(ui, |ui| {
+                ui.label("Rain rate");
+                ui.add(DragValue::new(&mut self.budget.rain_rate).range(0.0..=250.0).speed(0.5));
+                ui.label("mm/h");
+                ui.end_row();
 
-                    ui.label("Rx Power");
-                    self.rx_power.val_dbm = self.snr + thermal_noise_floor;
-                    ui.label(format!("{:.2}", self.rx_power.get_in_unit()));
-                    self.rx_power.unit_selector_ui("rx_power", ui);
-                })
+                ui.label("Polarization");
+                egui::ComboBox::new("polarization", "")
+                    .selected_text(self.budget.polarization.to_string())
+                    .show_ui(ui, |ui| {
+                        for polarization in [Polarization::Horizontal, Polarization::Vertical, Polarization::Circular] {
+                            ui.selectable_value(&mut self.budget.polarization, polarization, polarization.to_string());
+                        }
+                    });
+                ui.label("");
+                ui.end_row();
+
+                ui.label("Rain loss (ITU-R P.838)");
+                ui.label(format!("{:.precision$}", self.budget.rain_loss()));
+                ui.label("dB");
+                ui.end_row();
             });
         });
     }
 
-    fn ui_path_loss(&mut self, ui: &mut Ui) {
-        frame_styled(&ui).show(ui, |ui| {
-            ui.vertical(|ui| {
-                ui.heading("Free Space Path loss");
-                egui::Grid::new("path_loss").show(ui, |ui| {
-                    ui.selectable_value(
-                        &mut self.calculation_target,
-                        CalculationTarget::Distance,
-                        "Distance",
-                    );
-                    ui.add(DragValue::new(&mut self.distance).suffix(" m"));
-                    ui.end_row();
+    fn ui_sweeps(&mut self, ui: &mut Ui) {
+        ui.collapsing("Sweep", |ui| {
+            self.ui_frequency_sweep(ui);
+            ui.separator();
+            self.ui_distance_sweep(ui);
+        });
+    }
 
-                    ui.label("break distance");
-                    ui.add(DragValue::new(&mut self.d_break).suffix(" m"));
-                    ui.end_row();
+    /// Distributions of the link margin terms, the nominal margin and the tolerances of the
+    /// Tx power, the gains and the losses
+    fn margin_distributions(&self) -> Vec<calc::Normal> {
+        let mut distributions = vec![
+            calc::Normal { mean: self.budget.link_margin(), std_dev: 0.0 },
+            calc::Normal { mean: 0.0, std_dev: self.budget.tx_power_tolerance },
+        ];
+        distributions.extend(
+            self.budget.gains
+                .values()
+                .filter(|g| g.enabled)
+                .map(|g| calc::Normal { mean: 0.0, std_dev: g.tolerance }),
+        );
+        distributions.extend(
+            self.budget.losses
+                .values()
+                .filter(|l| l.applies(self.budget.condition))
+                .map(|l| calc::Normal { mean: 0.0, std_dev: l.tolerance }),
+        );
+        distributions
+    }
 
-                    ui.label("break exponent");
-                    ui.add(DragValue::new(&mut self.break_exponent));
+    fn ui_tolerances(&mut self, ui: &mut Ui) {
+        let precision = self.precision;
+        ui.collapsing("Tolerance analysis", |ui| {
+            ui.label("Standard deviation of the inputs");
+            let tolerance = |ui: &mut Ui, value: &mut f64| {
+                ui.add(DragValue::new(value).range(0.0..=f64::MAX).speed(0.05).prefix("± ").suffix(" dB"));
+            };
+            egui::Grid::new("tolerances").num_columns(2).striped(true).show(ui, |ui| {
+                ui.label("Tx power");
+                tolerance(ui, &mut self.budget.tx_power_tolerance);
+                ui.end_row();
+                for (name, gain) in self.budget.gains.iter_mut() {
+                    ui.label(name.as_str());
+                    tolerance(ui, &mut gain.tolerance);
                     ui.end_row();
-
-                    let path_loss = calc::friis::path_loss(self.distance, self.d_break, self.frequency, self.break_exponent);
-                    ui.label("Path Loss");
-                    ui.label(format!("{path_loss:.1} dBm"));
+                }
+                for (name, loss) in self.budget.losses.iter_mut() {
+                    ui.label(name.as_str());
+                    tolerance(ui, &mut loss.tolerance);
                     ui.end_row();
-                });
+                }
             });
-        });
-    }
-}
-
-impl eframe::App for LinkBudgetApp {
-    fn save(&mut self, storage: &mut dyn Storage) {
-        eframe::set_value(storage, eframe::APP_KEY, self);
+            ui.horizontal(|ui| {
+                ui.label("Samples");
+                ui.add(DragValue::new(&mut self.monte_carlo_samples).range(100..=1_000_000).speed(100.0));
+                let running = self.monte_carlo_job.is_some();
+                if ui.add_enabled(!running, egui::Button::new("Run")).clicked() {
+                    let samples = self.monte_carlo_samples;
+                    let distributions = self.margin_distributions();
+                    self.monte_carlo_job = Some(asynch::Background::spawn(ui.ctx(), move || {
+                        calc::monte_carlo(samples, &distributions)
+                    }));
+                }
+                if running {
+                    ui.spinner();
+                }
+            });
+            if self.monte_carlo_margins.is_empty() {
+                return;
+            }
+
+            let margins = &self.monte_carlo_margins;
+            let mean = margins.iter().sum::<f64>() / margins.len() as f64;
+            let worst_5 = calc::percentile(margins, 5.0);
+            let closes = margins.iter().filter(|m| **m >= 0.0).count() as f64 / margins.len() as f64;
+            ui.label(format!(
+                "Margin mean {mean:.precision$} dB, 5th percentile {worst_5:.precision$} dB, link closes in {:.1} % of the samples",
+                closes * 100.0,
+            ));
+
+            let (min, max) = margins.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), m| (min.min(*m), max.max(*m)));
+            let bins = 40;
+            let width = ((max - min) / bins as f64).max(0.01);
+            let mut counts = vec![0usize; bins];
+            for margin in margins {
+                let bin = ((margin - min) / width) as usize;
+                counts[bin.min(bins - 1)] += 1;
+            }
+            let bars: Vec<egui_plot::Bar> = counts
+                .iter()
+                .enumerate()
+                .map(|(i, count)| egui_plot::Bar::new(min + (i as f64 + 0.5) * width, *count as f64).width(width))
+                .collect();
+            Plot::new("margin_histogram")
+                .height(160.0)
+                .x_axis_label("Margin [dB]")
+                .y_axis_label("Samples")
+                .show(ui, |plot_ui| {
+                    plot_ui.bar_chart(egui_plot::BarChart::new(bars).name("margin"));
+                    plot_ui.vline(VLine::new(worst_5).name("5th percentile"));
+                    plot_ui.vline(VLine::new(mean).name("mean"));
+                });
+            if ui.button("Export data CSV").clicked() {
+                let rows: Vec<Vec<String>> = margins
+                    .iter()
+                    .enumerate()
+                    .map(|(i, margin)| vec![i.to_string(), margin.to_string()])
+                    .collect();
+                save_csv(ui.ctx(), "monte_carlo.csv", export::series_to_csv(&["sample", "margin_db"], &rows));
+            }
+        });
+    }
+
+    fn ui_distance_sweep(&mut self, ui: &mut Ui) {
+        let precision = self.precision;
+        ui.horizontal(|ui| {
+            ui.label("Distance from");
+            ui.add(DragValue::new(&mut self.sweep_d_start).range(0.001..=f64::MAX).suffix(" m"));
+            ui.label("to");
+            ui.add(DragValue::new(&mut self.sweep_d_stop).range(0.001..=f64::MAX).suffix(" m"));
+        });
+        let sweep = self.budget.sweep_over_distance(self.sweep_d_start, self.sweep_d_stop, self.sweep_steps);
+        // the x axis is log10 of the distance
+        let rx_power: Vec<[f64; 2]> = sweep
+            .iter()
+            .map(|(distance, rx_power, _)| [f64::log10(*distance), *rx_power])
+            .collect();
+        let snr: Vec<[f64; 2]> = sweep
+            .iter()
+            .map(|(distance, _, snr)| [f64::log10(*distance), *snr])
+            .collect();
+        let crossover = sweep
+            .iter()
+            .find(|(_, _, snr)| *snr < self.budget.required_snr)
+            .map(|(distance, _, _)| *distance);
+        let required_snr = self.budget.required_snr;
+        Plot::new("distance_sweep")
+            .height(200.0)
+            .legend(egui_plot::Legend::default())
+            .x_axis_label("Distance")
+            .y_axis_label("dBm / dB")
+            .x_axis_formatter(|mark, _range| format_distance(f64::powf(10.0, mark.value)))
+            .label_formatter(|name, point| {
+                format!("{name}\n{}\n{:.precision$}", format_distance(f64::powf(10.0, point.x)), point.y)
+            })
+            .show(ui, |plot_ui| {
+                plot_ui.line(Line::new(rx_power).name("Rx power [dBm]"));
+                plot_ui.line(Line::new(snr).name("SNR [dB]"));
+                plot_ui.hline(HLine::new(required_snr).name("required SNR"));
+                if let Some(distance) = crossover {
+                    plot_ui.vline(VLine::new(f64::log10(distance)).name("required SNR"));
+                    plot_ui.text(Text::new(
+                        PlotPoint::new(f64::log10(distance), required_snr),
+                        format!("  {}", format_distance(distance)),
+                    ).anchor(egui::Align2::LEFT_BOTTOM));
+                }
+            });
+        if ui.button("Export data CSV").clicked() {
+            let rows: Vec<Vec<String>> = sweep
+                .iter()
+                .map(|(distance, rx_power, snr)| vec![distance.to_string(), rx_power.to_string(), snr.to_string()])
+                .collect();
+            let csv = export::series_to_csv(&["distance_m", "rx_power_dbm", "snr_db"], &rows);
+            save_csv(ui.ctx(), "distance_sweep.csv", csv);
+        }
+    }
+
+    fn ui_frequency_sweep(&mut self, ui: &mut Ui) {
+        let precision = self.precision;
+        ui.horizontal(|ui| {
+            ui.label("Frequency from");
+            ui.add(
+                prefix_drag_value(&mut self.sweep_f_start, SiPrefix::Auto)
+                    .range(1.0..=f64::MAX)
+                    .speed(1e6),
+            );
+            ui.label("Hz to");
+            ui.add(
+                prefix_drag_value(&mut self.sweep_f_stop, SiPrefix::Auto)
+                    .range(1.0..=f64::MAX)
+                    .speed(1e6),
+            );
+            ui.label("Hz in");
+            ui.add(DragValue::new(&mut self.sweep_steps).range(2..=10000));
+            ui.label("steps");
+        });
+        let sweep = self.budget.sweep_path_loss(self.sweep_f_start, self.sweep_f_stop, self.sweep_steps);
+        // the x axis is log10 of the frequency
+        let points: Vec<[f64; 2]> = sweep
+            .iter()
+            .filter(|(_, path_loss)| path_loss.is_finite())
+            .map(|(frequency, path_loss)| [f64::log10(*frequency), *path_loss])
+            .collect();
+        Plot::new("frequency_sweep")
+            .height(200.0)
+            .x_axis_label("Frequency")
+            .y_axis_label("Path loss [dB]")
+            .x_axis_formatter(|mark, _range| format_frequency(f64::powf(10.0, mark.value)))
+            .label_formatter(|_name, point| {
+                format!("{}\n{:.precision$} dB", format_frequency(f64::powf(10.0, point.x)), point.y)
+            })
+            .show(ui, |plot_ui| {
+                plot_ui.line(Line::new(points).name("path loss"));
+            });
+        if ui.button("Export data CSV").clicked() {
+            let rows: Vec<Vec<String>> = sweep
+                .iter()
+                .map(|(frequency, path_loss)| vec![frequency.to_string(), path_loss.to_string()])
+                .collect();
+            save_csv(ui.ctx(), "frequency_sweep.csv", export::series_to_csv(&["frequency_hz", "path_loss_db"], &rows));
+        }
+    }
+
+    /// Every term of the budget as name and value, values are in dB, powers in dBm. Losses are
+    /// negative so that the rows from Tx power to path loss sum up to the Rx power.
+    pub fn to_csv_rows(&self) -> Vec<(String, f64)> {
+        let mut terms = self.budget.breakdown();
+        terms[1..].sort_by_key(|term| term.kind as u8);
+        let mut rows: Vec<(String, f64)> = terms
+            .into_iter()
+            .map(|term| match term.kind {
+                TermKind::TxPower => { (format!("{} [dBm]", term.name), term.value) }
+                _ => { (term.name, term.value) }
+            })
+            .collect();
+        rows.push(("noise floor [dBm]".to_string(), self.budget.noise_floor()));
+        rows.push(("implementation margin".to_string(), self.budget.implementation_margin));
+        rows.push(("SNR".to_string(), self.budget.snr));
+        rows.push(("margin".to_string(), self.budget.link_margin()));
+        rows
+    }
+
+    /// The whole budget as aligned plain text for chat or email: parameters, gains, losses and
+    /// the resulting margin
+    pub fn summary_text(&self) -> String {
+        let precision = self.precision;
+        let mut lines = vec![
+            format!("Link budget, solved for {}", self.budget.target),
+            format!("{:<24}{}", "Frequency", format_frequency(self.budget.frequency)),
+            format!("{:<24}{}", "Bandwidth", format_frequency(self.budget.bandwidth)),
+            format!("{:<24}{:.precision$} dBm", "Noise floor", self.budget.noise_floor()),
+            format!("{:<24}{:.precision$} dBm", "Tx power", self.budget.tx_power),
+            format!("{:<24}{:.precision$} dBm", "EIRP", self.budget.eirp()),
+            format!("{:<24}{}", "Distance", format_distance(self.budget.distance)),
+            format!("{:<24}{:.precision$} dB", "Path loss", self.budget.path_loss()),
+            format!("{:<24}{:.precision$} dB", "Excess loss", self.budget.excess_loss()),
+            "Gains".to_string(),
+        ];
+        for (name, gain) in self.budget.gains.iter().filter(|(_, g)| g.enabled) {
+            lines.push(format!("  {:<22}{:+.precision$} dB", name, gain.value_at(self.budget.frequency)));
+        }
+        for (name, antenna) in &self.budget.antennas {
+            lines.push(format!("  {:<22}{:+.precision$} dB", name, antenna.gain(self.budget.frequency, self.budget.physics)));
+        }
+        lines.push("Losses".to_string());
+        for (name, loss) in self.budget.losses.iter().filter(|(_, l)| l.applies(self.budget.condition)) {
+            lines.push(format!("  {:<22}{:+.precision$} dB", name, -loss.value_at(self.budget.frequency)));
+        }
+        lines.extend([
+            format!("{:<24}{:.precision$} dB", "Implementation margin", self.budget.implementation_margin),
+            format!("{:<24}{:.precision$} dB", "SNR", self.budget.snr),
+            format!("{:<24}{:.precision$} dB", "Required SNR", self.budget.required_snr),
+            format!("{:<24}{:+.precision$} dB", "Margin", self.budget.link_margin()),
+            self.verdict(),
+        ]);
+        lines.join("\n") + "\n"
+    }
+
+    /// Save every term of the budget as CSV
+    fn export_csv(&self, ctx: &Context) {
+        let rows: Vec<Vec<String>> = self
+            .to_csv_rows()
+            .into_iter()
+            .map(|(name, value)| vec![name, value.to_string()])
+            .collect();
+        save_csv(ctx, "link_budget.csv", export::series_to_csv(&["term", "value"], &rows));
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(&StateFile { version: STATE_FILE_VERSION, state: self })
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let value: serde_json::Value = serde_json::from_str(json).map_err(|e| format!("The file is not valid JSON: {e}"))?;
+        match value.get("version").and_then(|v| v.as_u64()) {
+            Some(version) if version == STATE_FILE_VERSION as u64 => {}
+            Some(version) => {
+                return Err(format!("The file has version {version}, this version of the app reads version {STATE_FILE_VERSION}"));
+            }
+            None => { return Err("The file is not a link budget".to_string()); }
+        }
+        let state = value.get("state").cloned().unwrap_or_default();
+        Self::from_state(state).map_err(|e| format!("The file does not contain a valid link budget: {e}"))
+    }
+
+    /// The app of a serialized state, states of older versions are migrated
+    fn from_state(state: serde_json::Value) -> Result<Self, serde_json::Error> {
+        let mut app: Self = serde_json::from_value(state.clone())?;
+        if let (Ok(flat), Ok(budget)) = (serde_json::from_value(state.clone()), serde_json::from_value(state)) {
+            app.migrate(flat, budget);
+        }
+        Ok(app)
+    }
+
+    /// Save the state to a file, on the web where there is no file dialog it is copied
+    fn export_json(&self, ctx: &Context) {
+        let json = match self.to_json() {
+            Ok(json) => json,
+            Err(e) => {
+                log::error!("Failed to serialize the state: {e}");
+                return;
+            }
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let _ = ctx;
+            let file = rfd::FileDialog::new()
+                .set_file_name("link_budget.json")
+                .add_filter("JSON", &["json"])
+                .save_file();
+            if let Some(path) = file {
+                if let Err(e) = std::fs::write(&path, json) {
+                    log::error!("Failed to write {}: {e}", path.display());
+                }
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        ctx.copy_text(json);
+    }
+
+    /// Save the screenshot requested by Export PNG once it arrives
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_screenshot(&mut self, ctx: &Context) {
+        if !self.screenshot_pending {
+            return;
+        }
+        let screenshot = ctx.input(|i| {
+            i.events.iter().find_map(|event| match event {
+                egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                _ => None,
+            })
+        });
+        let Some(screenshot) = screenshot else {
+            return;
+        };
+        self.screenshot_pending = false;
+        let file = rfd::FileDialog::new()
+            .set_file_name("link_budget.png")
+            .add_filter("PNG", &["png"])
+            .save_file();
+        if let Some(path) = file {
+            if let Err(e) = export::write_png(&path, &screenshot) {
+                log::error!("Failed to write {}: {e}", path.display());
+            }
+        }
+    }
+
+    /// Replace the state with one loaded from a file, the preferences are kept
+    #[cfg(not(target_arch = "wasm32"))]
+    fn import_json(&mut self) {
+        let Some(path) = rfd::FileDialog::new().add_filter("JSON", &["json"]).pick_file() else {
+            return;
+        };
+        let imported = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))
+            .and_then(|json| Self::from_json(&json));
+        match imported {
+            Ok(mut state) => {
+                state.keep_preferences(self);
+                *self = state;
+            }
+            Err(e) => { self.import_error = Some(e); }
+        }
+    }
+
+    /// Insert a gain from an antenna pattern CSV file, named after the name field or the file
+    #[cfg(not(target_arch = "wasm32"))]
+    fn import_pattern(&mut self) {
+        let Some(path) = rfd::FileDialog::new().add_filter("CSV", &["csv", "txt"]).pick_file() else {
+            return;
+        };
+        let table = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))
+            .and_then(|csv| parse_pattern_csv(&csv));
+        match table {
+            Ok(table) => {
+                let name = if self.gain_name.trim().is_empty() {
+                    path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default()
+                } else {
+                    std::mem::take(&mut self.gain_name)
+                };
+                let mut gain = Gain::pattern(table);
+                gain.category = self.gain_category.trim().to_string();
+                self.budget.gains.insert(name, gain);
+            }
+            Err(e) => { self.import_error = Some(e); }
+        }
+    }
+
+    /// Link margin colored green above the comfortable margin, amber when it barely closes and
+    /// red when it does not close
+    fn ui_status_bar(&self, ui: &mut Ui) {
+        let precision = self.precision;
+        let margin = self.budget.link_margin();
+        let color = if margin > COMFORTABLE_MARGIN {
+            egui::Color32::from_rgb(0, 160, 0)
+        } else if margin >= 0.0 {
+            ui.visuals().warn_fg_color
+        } else {
+            ui.visuals().error_fg_color
+        };
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(format!("Margin: {margin:+.precision$} dB")).strong().color(color));
+            ui.separator();
+            ui.label(format!("SNR {:.precision$} dB", self.budget.snr));
+            ui.separator();
+            ui.label(format_distance(self.budget.distance));
+            ui.separator();
+            ui.label(format!("Tx {:.precision$} dBm", self.budget.tx_power));
+        });
+    }
+
+    /// Move the preferences over from the state this one replaces. The cable library, the
+    /// scenarios, the history, the log and the display settings are not part of the budget.
+    fn keep_preferences(&mut self, from: &mut Self) {
+        self.cable_types = std::mem::take(&mut from.cable_types);
+        self.scenarios = std::mem::take(&mut from.scenarios);
+        self.history = std::mem::take(&mut from.history);
+        self.log = std::mem::take(&mut from.log);
+        self.view = std::mem::take(&mut from.view);
+        self.step_scale = from.step_scale;
+        self.precision = from.precision;
+        self.path_loss_as_gain = from.path_loss_as_gain;
+        self.theme = from.theme;
+    }
+
+    fn reset(&mut self, scope: ResetScope) {
+        if scope == ResetScope::Tables {
+            self.budget.gains.clear();
+            self.budget.losses.clear();
+            self.budget.antennas.clear();
+            return;
+        }
+        let mut defaults = Self::default();
+        defaults.keep_preferences(self);
+        if scope == ResetScope::Parameters {
+            defaults.budget.gains = std::mem::take(&mut self.budget.gains);
+            defaults.budget.losses = std::mem::take(&mut self.budget.losses);
+            defaults.budget.antennas = std::mem::take(&mut self.budget.antennas);
+        }
+        *self = defaults;
+    }
+
+    fn ui_reset_window(&mut self, ctx: &Context) {
+        if !self.show_reset {
+            return;
+        }
+        let mut scope = None;
+        egui::Window::new("Reset")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("Restore the defaults? Undo brings the budget back.");
+                ui.horizontal(|ui| {
+                    if ui.button("Everything").clicked() {
+                        scope = Some(ResetScope::Everything);
+                    }
+                    if ui.button("Parameters").on_hover_text("Keep the gains, losses and antennas").clicked() {
+                        scope = Some(ResetScope::Parameters);
+                    }
+                    if ui.button("Gains and losses").on_hover_text("Keep the parameters").clicked() {
+                        scope = Some(ResetScope::Tables);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.show_reset = false;
+                    }
+                });
+            });
+        if let Some(scope) = scope {
+            self.reset(scope);
+            self.show_reset = false;
+        }
+    }
+
+    fn ui_import_error_window(&mut self, ctx: &Context) {
+        let Some(error) = &self.import_error else {
+            return;
+        };
+        let mut close = false;
+        egui::Window::new("Import failed")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+                if ui.button("OK").clicked() {
+                    close = true;
+                }
+            });
+        if close {
+            self.import_error = None;
+        }
+    }
+
+    fn ui_waterfall(&self, ui: &mut Ui) {
+        ui.collapsing("Waterfall", |ui| {
+            let terms = self.budget.waterfall_terms();
+            let mut level = 0.0;
+            let mut bars: Vec<egui_plot::Bar> = terms
+                .iter()
+                .enumerate()
+                .map(|(i, (name, value))| {
+                    let fill = if *value >= 0.0 { egui::Color32::DARK_GREEN } else { egui::Color32::DARK_RED };
+                    let bar = egui_plot::Bar::new(i as f64, *value).base_offset(level).name(name).fill(fill);
+                    level += value;
+                    bar
+                })
+                .collect();
+            bars.push(
+                egui_plot::Bar::new(terms.len() as f64, level)
+                    .name("margin")
+                    .fill(egui::Color32::from_rgb(70, 110, 200)),
+            );
+            let mut names: Vec<String> = terms.into_iter().map(|(name, _)| name).collect();
+            names.push("margin".to_string());
+            Plot::new("waterfall")
+                .height(220.0)
+                .y_axis_label("dBm")
+                .x_axis_formatter(move |mark, _range| {
+                    if mark.value.fract() != 0.0 || mark.value < 0.0 {
+                        return String::new();
+                    }
+                    names.get(mark.value as usize).cloned().unwrap_or_default()
+                })
+                .show(ui, |plot_ui| {
+                    plot_ui.bar_chart(egui_plot::BarChart::new(bars).width(0.7));
+                    plot_ui.hline(HLine::new(0.0));
+                });
+        });
+    }
+
+    fn ui_margin_explanation(&self, ui: &mut Ui) {
+        let precision = self.precision;
+        ui.label("margin = Tx power + gains − path loss − losses − noise floor − required SNR − implementation margin");
+        let mut terms = self.budget.margin_terms();
+        terms.sort_by(|a, b| b.value.abs().total_cmp(&a.value.abs()));
+        egui::Grid::new("margin_explanation").num_columns(3).striped(true).show(ui, |ui| {
+            for term in terms {
+                ui.label(term.name.as_str());
+                ui.label(format!("{:+.precision$} dB", term.value));
+                ui.label(term.kind.description());
+                ui.end_row();
+            }
+            ui.strong("margin");
+            ui.strong(format!("{:+.precision$} dB", self.budget.link_margin()));
+            ui.end_row();
+        });
+    }
+
+    /// Name and step of the input the arrow keys move, the one the calculation target is traded
+    /// against. The target itself is solved and cannot be moved. On a slant path the distance
+    /// follows the elevation, which is moved instead.
+    fn nudged_parameter(&self, modifiers: egui::Modifiers) -> (&'static str, f64, String) {
+        let scale = if modifiers.shift {
+            10.0
+        } else if modifiers.ctrl {
+            0.1
+        } else {
+            1.0
+        } * self.step_scale;
+        if self.budget.slant_elevation().is_some() {
+            return ("elevation", scale, "°".to_string());
+        }
+        match self.budget.target {
+            Target::Snr | Target::TxPower | Target::Bandwidth => {
+                ("distance", self.distance_unit.step() * scale, self.distance_unit.to_string())
+            }
+            Target::Distance => { ("Tx power", 0.1 * scale, "dB".to_string()) }
+        }
+    }
+
+    /// Move the nudged parameter with the up and down arrow keys, Shift for coarse and Ctrl for
+    /// fine steps. Keys go to a focused text field or drag value instead.
+    fn nudge_with_keys(&mut self, ctx: &Context) {
+        if ctx.memory(|memory| memory.focused().is_some()) {
+            return;
+        }
+        let (direction, modifiers) = ctx.input(|i| {
+            let direction = i.num_presses(egui::Key::ArrowUp) as f64 - i.num_presses(egui::Key::ArrowDown) as f64;
+            (direction, i.modifiers)
+        });
+        if direction == 0.0 {
+            return;
+        }
+        let (_, step, _) = self.nudged_parameter(modifiers);
+        if self.budget.slant_elevation().is_some() {
+            self.budget.elevation = (self.budget.elevation + direction * step).clamp(0.0, 90.0);
+            return;
+        }
+        match self.budget.target {
+            Target::Snr | Target::TxPower | Target::Bandwidth => {
+                let mut distance = Length { val_m: self.budget.distance, unit: self.distance_unit };
+                distance.value_from_unit((distance.get_in_unit() + direction * step).max(0.0));
+                self.budget.distance = distance.val_m;
+            }
+            Target::Distance => { self.budget.tx_power += direction * step; }
+        }
+    }
+
+    /// Record the budget for undo once an edit is finished, not on every frame of a drag or
+    /// every keystroke
+    fn record_history(&mut self, ctx: &Context) {
+        let editing = ctx.input(|i| i.pointer.any_down()) || ctx.memory(|memory| memory.focused().is_some());
+        if editing {
+            return;
+        }
+        if let Ok(state) = serde_json::to_string(&self.budget) {
+            self.history.record(state);
+        }
+    }
+
+    /// Append the current solve result to the log
+    fn log_result(&mut self, event: &str) {
+        let timestamp = web_time::SystemTime::now()
+            .duration_since(web_time::UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        self.log.push(LogEntry {
+            timestamp,
+            event: event.to_string(),
+            target: self.budget.target,
+            frequency: self.budget.frequency,
+            distance: self.budget.distance,
+            tx_power: self.budget.tx_power,
+            snr: self.budget.snr,
+            margin: self.budget.link_margin(),
+        });
+    }
+
+    /// Log the result when the calculation target changed since the last frame
+    fn log_target_change(&mut self) {
+        let previous = self.logged_target.replace(self.budget.target);
+        if previous.is_some_and(|target| target != self.budget.target) {
+            self.log_result(&format!("Target {}", self.budget.target));
+        }
+    }
+
+    fn ui_log(&mut self, ui: &mut Ui) {
+        let precision = self.precision;
+        ui.collapsing("Log", |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Snapshot").on_hover_text("Log the current result").clicked() {
+                    self.log_result("Snapshot");
+                }
+                if ui.add_enabled(!self.log.is_empty(), egui::Button::new("Clear")).clicked() {
+                    self.log.clear();
+                }
+                if ui.add_enabled(!self.log.is_empty(), egui::Button::new("Export data CSV")).clicked() {
+                    let rows: Vec<Vec<String>> = self
+                        .log
+                        .iter()
+                        .map(|entry| {
+                            vec![
+                                format_timestamp(entry.timestamp),
+                                entry.event.clone(),
+                                entry.frequency.to_string(),
+                                entry.distance.to_string(),
+                                entry.tx_power.to_string(),
+                                entry.snr.to_string(),
+                                entry.margin.to_string(),
+                            ]
+                        })
+                        .collect();
+                    let header = ["time", "event", "frequency_hz", "distance_m", "tx_power_dbm", "snr_db", "margin_db"];
+                    save_csv(ui.ctx(), "log.csv", export::series_to_csv(&header, &rows));
+                }
+            });
+            egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                egui::Grid::new("log").num_columns(7).striped(true).show(ui, |ui| {
+                    for heading in ["Time", "Event", "Frequency", "Distance", "Tx power", "SNR", "Margin"] {
+                        ui.strong(heading);
+                    }
+                    ui.end_row();
+                    for entry in self.log.iter().rev() {
+                        ui.label(format_timestamp(entry.timestamp));
+                        ui.label(entry.event.as_str());
+                        ui.label(format_frequency(entry.frequency));
+                        ui.label(format_distance(entry.distance));
+                        ui.label(format!("{:.precision$} dBm", entry.tx_power));
+                        ui.label(format!("{:.precision$} dB", entry.snr));
+                        ui.label(format!("{:+.precision$} dB", entry.margin));
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+    }
+
+    fn undo(&mut self) {
+        if let Some(snapshot) = self.history.undo().and_then(|state| serde_json::from_str(&state).ok()) {
+            self.restore(snapshot);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(snapshot) = self.history.redo().and_then(|state| serde_json::from_str(&state).ok()) {
+            self.restore(snapshot);
+        }
+    }
+
+    /// Ctrl+Z and Ctrl+Y, a focused text field handles them itself
+    fn undo_with_keys(&mut self, ctx: &Context) {
+        if ctx.memory(|memory| memory.focused().is_some()) {
+            return;
+        }
+        let undo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+        let redo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y);
+        if ctx.input_mut(|i| i.consume_shortcut(&undo)) {
+            self.undo();
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&redo)) {
+            self.redo();
+        }
+    }
+
+    /// Take the result of the Monte Carlo run once the worker is done
+    fn poll_monte_carlo(&mut self) {
+        if let Some(margins) = self.monte_carlo_job.as_ref().and_then(|job| job.poll()) {
+            self.monte_carlo_margins = margins;
+            self.monte_carlo_job = None;
+        }
+    }
+
+    /// The selected band preset if the frequency is within its band
+    fn band(&self) -> Option<(&'static str, &'static BandPreset)> {
+        BAND_PRESETS
+            .iter()
+            .find(|(name, _)| *name == self.band_preset)
+            .filter(|(_, preset)| (preset.band.0..=preset.band.1).contains(&self.budget.frequency))
+            .map(|(name, preset)| (*name, preset))
+    }
+
+    /// Take the frequency and bandwidth of the preset and lower the Tx power to keep the EIRP
+    /// within its limit
+    fn apply_band_preset(&mut self, name: &str) {
+        let Some((_, preset)) = BAND_PRESETS.iter().find(|(preset_name, _)| *preset_name == name) else {
+            return;
+        };
+        self.band_preset = name.to_string();
+        self.budget.frequency = preset.frequency;
+        self.budget.bandwidth = preset.bandwidth;
+        let excess = self.budget.eirp() - preset.max_eirp;
+        if excess > 0.0 {
+            self.budget.tx_power -= excess;
+        }
+    }
+
+    /// Problems that make the requested link infeasible
+    pub fn warnings(&self) -> Vec<String> {
+        let precision = self.precision;
+        let mut warnings = Vec::new();
+        if !self.budget.total_sum().is_finite() {
+            warnings.push("The budget has no finite solution, check the inputs.".to_string());
+        }
+        if self.budget.use_data_rate {
+            if !self.budget.data_rate_achievable() {
+                warnings.push("The data rate cannot be carried in this bandwidth at any SNR.".to_string());
+            } else if self.budget.data_rate > calc::shannon_capacity(self.budget.bandwidth, self.budget.snr) {
+                warnings.push("The data rate exceeds the Shannon capacity at the achieved SNR.".to_string());
+            }
+        }
+        if self.budget.propagation_model == PropagationModel::Friis {
+            let (d_break, break_exponent) = calc::friis::clamp_break(self.budget.d_break, self.budget.break_exponent);
+            if d_break != self.budget.d_break || break_exponent != self.budget.break_exponent {
+                warnings.push("The break distance or exponent is not physical and was clamped.".to_string());
+            }
+        }
+        if let Some((name, preset)) = self.band() {
+            let eirp = self.budget.eirp();
+            if eirp > preset.max_eirp + 1e-9 {
+                warnings.push(format!(
+                    "The EIRP of {eirp:.precision$} dBm exceeds the {:.precision$} dBm limit of {name}.",
+                    preset.max_eirp,
+                ));
+            }
+        }
+        match self.budget.target {
+            // the Friis inverse stops at 1 m, the budget does not balance there
+            Target::Distance
+                if self.budget.distance < calc::friis::MIN_DISTANCE
+                    || (self.budget.distance == calc::friis::MIN_DISTANCE && self.budget.total_sum() < -1e-6) =>
+            {
+                warnings.push("The required SNR cannot be met even at 1 m distance.".to_string());
+            }
+            Target::TxPower if self.budget.tx_power > 90.0 => {
+                warnings.push("The required Tx power exceeds 1 MW, the link is not feasible.".to_string());
+            }
+            Target::Bandwidth if self.budget.use_sensitivity || (self.budget.use_data_rate && self.budget.use_ebn0) => {
+                warnings.push("The bandwidth cancels out with the Eb/N0 or the sensitivity, it is not solved.".to_string());
+            }
+            Target::Bandwidth if self.budget.use_data_rate && !self.budget.solve_bandwidth().is_finite() => {
+                warnings.push("The data rate cannot be carried at any bandwidth with this budget.".to_string());
+            }
+            _ => {}
+        }
+        warnings
+    }
+
+    /// Plain language summary of the budget
+    pub fn verdict(&self) -> String {
+        let precision = self.precision;
+        let margin = self.budget.link_margin();
+        let closure = if margin >= 0.0 {
+            format!("Link closes with {margin:.precision$} dB margin")
+        } else {
+            format!("Link fails by {:.precision$} dB", -margin)
+        };
+
+        let dominant = self
+            .budget()
+            .breakdown()
+            .into_iter()
+            .filter(|term| term.kind != TermKind::TxPower && term.value < 0.0)
+            .min_by(|a, b| a.value.total_cmp(&b.value));
+        match dominant {
+            Some(term) if term.kind == TermKind::PathLoss => {
+                format!("{closure}; limited by path loss at {}.", format_distance(self.budget.distance))
+            }
+            Some(term) => format!("{closure}; limited by {}.", term.name),
+            None => format!("{closure}."),
+        }
+    }
+
+    fn ui_parameters(&mut self, ui: &mut Ui) {
+        let precision = self.precision;
+        frame_styled(ui).show(ui, |ui| {
+            ui.vertical(|ui| {
+                ui.heading("Parameters");
+                egui::Grid::new("base_data").num_columns(3).show(ui, |ui| {
+                    ui.label("Solve for")
+                        .on_hover_text("The solved value is read only, all others are inputs");
+                    egui::ComboBox::new("calculation_target", "")
+                        .selected_text(self.budget.target.to_string())
+                        .show_ui(ui, |ui| {
+                            for target in Target::ALL {
+                                ui.selectable_value(&mut self.budget.target, target, target.to_string());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.checkbox(&mut self.budget.separate_temperatures, "T ant + T rx")
+                        .on_hover_text("Noise of the antenna temperature plus the receiver noise temperature instead of the temperature and noise figure");
+                    ui.end_row();
+
+                    if self.budget.separate_temperatures {
+                        ui.label("Antenna temperature")
+                            .on_hover_text("About 30 K pointing at the cold sky, 290 K at the ground");
+                        ui.add(DragValue::new(&mut self.budget.antenna_temperature).range(0.0..=f64::MAX));
+                        ui.label("K");
+                        ui.end_row();
+
+                        ui.label("Receiver temperature")
+                            .on_hover_text("Noise temperature of the receiver, T = 290 K (10^(NF / 10) - 1)");
+                        ui.add(DragValue::new(&mut self.budget.receiver_temperature).range(0.0..=f64::MAX));
+                        ui.label("K");
+                        ui.end_row();
+                    } else {
+                        ui.label("Temperature")
+                            .on_hover_text("Noise temperature of the source, 290 K by convention");
+                        let mut temperature = self.temperature_unit.from_kelvin(self.budget.temperature);
+                        if stepper(ui, &mut temperature, self.step_scale, "").changed() {
+                            self.budget.temperature = self.temperature_unit.to_kelvin(temperature);
+                        }
+                        egui::ComboBox::new("temperature_unit", "").width(60.0)
+                            .selected_text(self.temperature_unit.to_string())
+                            .show_ui(ui, |ui| {
+                                for unit in [TemperatureUnit::Kelvin, TemperatureUnit::Celsius, TemperatureUnit::Fahrenheit] {
+                                    ui.selectable_value(&mut self.temperature_unit, unit, unit.to_string());
+                                }
+                            });
+                        ui.end_row();
+                    }
+
+                    target_selector(ui, &mut self.budget.target, Target::Bandwidth, "Bandwidth");
+                    ui.add_enabled(
+                        self.budget.target != Target::Bandwidth,
+                        prefix_drag_value(&mut self.budget.bandwidth, self.bandwidth_prefix)
+                            .range(0.0..=f64::MAX)
+                            .speed(1e6),
+                    )
+                    .on_hover_text("Noise bandwidth of the receiver, the noise power grows with 10 log10(B)");
+                    self.bandwidth_prefix.selector_ui("bandwidth_prefix", ui);
+                    ui.end_row();
+
+                    if !self.budget.separate_temperatures {
+                        ui.label("Noise figure")
+                            .on_hover_text("Noise the receiver adds, NF = 10 log10(F) with F = SNR in / SNR out");
+                        ui.add_enabled(!self.budget.noise_cascade, DragValue::new(&mut self.budget.noise_figure).range(0.0..=f64::MAX).speed(0.1))
+                            .on_disabled_hover_text("Cascade of the receive chain stages");
+                        ui.label("dB");
+                        ui.end_row();
+                    }
+
+                    let thermal_noise_floor = self.budget.noise_floor();
+                    ui.label("System noise floor")
+                        .on_hover_text("N = 10 log10(k T B / 1 mW) + NF, -174 dBm/Hz at 290 K");
+                    ui.label(format!("{thermal_noise_floor:.precision$}"));
+                    ui.label("dBm");
+                    ui.end_row();
+
+                    ui.label("Frequency")
+                        .on_hover_text("Carrier frequency, the free space loss grows with 20 log10(f)");
+                    ui.add(
+                        prefix_drag_value(&mut self.budget.frequency, self.frequency_prefix)
+                            .range(1.0..=f64::MAX)
+                            .speed(1e6),
+                    );
+                    self.frequency_prefix.selector_ui("frequency_prefix", ui);
+                    ui.end_row();
+
+                    let mut wavelength = calc::lambda(self.budget.frequency, self.budget.physics);
+                    ui.label("Wavelength")
+                        .on_hover_text("λ = c / (n f), entering it sets the frequency");
+                    let response = ui.add(DragValue::new(&mut wavelength).range(1e-6..=f64::MAX).speed(0.001).max_decimals(4))
+                        .on_hover_text(format!("λ/2 = {:.4} m, λ/4 = {:.4} m", wavelength / 2.0, wavelength / 4.0));
+                    if response.changed() {
+                        self.budget.frequency = calc::frequency_from_lambda(wavelength, self.budget.physics);
+                    }
+                    ui.label("m");
+                    ui.end_row();
+
+                    ui.label("Refractive index")
+                        .on_hover_text("Of the medium, 1 for vacuum and air. Scales the wavelength and the delay.");
+                    ui.add(DragValue::new(&mut self.budget.physics.refractive_index).range(1.0..=100.0).speed(0.01).max_decimals(3))
+                        .on_hover_text(format!("Velocity factor {:.3}", 1.0 / self.budget.physics.refractive_index));
+                    ui.label("");
+                    ui.end_row();
+
+                    ui.label("Margin slope");
+                    ui.label(format!("{:+.precision$}", self.budget.margin_frequency_sensitivity() * 100e6))
+                        .on_hover_text("Change of the link margin when moving the frequency up");
+                    ui.label("dB/100 MHz");
+                    ui.end_row();
+
+                    target_selector(ui, &mut self.budget.target, Target::Snr, "SNR");
+                    ui.add_enabled_ui(self.budget.target != Target::Snr, |ui| {
+                        stepper(ui, &mut self.budget.snr, 0.1 * self.step_scale, "");
+                    });
+                    ui.label("dB");
+                    ui.end_row();
+
+                    ui.radio_value(&mut self.budget.use_sensitivity, false, "Required SNR");
+                    ui.add_enabled_ui(!self.budget.use_data_rate && !self.budget.use_sensitivity, |ui| {
+                        stepper(ui, &mut self.budget.required_snr, 0.1 * self.step_scale, "");
+                    });
+                    ui.label("dB");
+                    ui.end_row();
+
+                    ui.radio_value(&mut self.budget.use_sensitivity, true, "Rx sensitivity")
+                        .on_hover_text("Derive the required SNR from the sensitivity minus the noise floor");
+                    ui.add_enabled_ui(self.budget.use_sensitivity, |ui| {
+                        stepper(ui, &mut self.budget.rx_sensitivity, 0.1 * self.step_scale, "");
+                    });
+                    ui.label("dBm");
+                    ui.end_row();
+
+                    ui.strong("Implementation margin")
+                        .on_hover_text("Safety margin subtracted from the SNR, kept apart from the losses");
+                    stepper(ui, &mut self.budget.implementation_margin, 0.1 * self.step_scale, "");
+                    ui.label("dB");
+                    ui.end_row();
+
+                    let margin = self.budget.link_margin();
+                    ui.label("Margin")
+                        .on_hover_text("Achieved SNR minus required SNR, the link closes when it is not negative");
+                    if margin >= 0.0 {
+                        ui.colored_label(egui::Color32::from_rgb(0, 160, 0), format!("✔ {margin:+.precision$}"));
+                    } else {
+                        ui.colored_label(ui.visuals().error_fg_color, format!("❌ {margin:+.precision$}"));
+                    }
+                    ui.label("dB");
+                    ui.end_row();
+
+                    ui.checkbox(&mut self.budget.use_data_rate, "Data rate");
+                    ui.add_enabled(
+                        self.budget.use_data_rate,
+                        prefix_drag_value(&mut self.budget.data_rate, SiPrefix::Auto)
+                            .range(0.0..=f64::MAX)
+                            .speed(1e5),
+                    );
+                    ui.label("bit/s");
+                    ui.end_row();
+
+                    if self.budget.use_data_rate && !self.budget.data_rate_achievable() {
+                        ui.label("");
+                        ui.colored_label(ui.visuals().error_fg_color, "Not achievable in this bandwidth");
+                        ui.end_row();
+                    }
+
+                    if self.budget.use_data_rate {
+                        ui.checkbox(&mut self.budget.use_ebn0, "Required Eb/N0")
+                            .on_hover_text("Derive the required SNR from Eb/N0 instead of the Shannon limit");
+                        ui.add_enabled_ui(self.budget.use_ebn0, |ui| {
+                            if stepper(ui, &mut self.budget.required_ebn0, 0.1 * self.step_scale, "").changed() {
+                                self.modulation = Modulation::Custom;
+                            }
+                        });
+                        ui.label("dB");
+                        ui.end_row();
+
+                        ui.label("Modulation");
+                        ui.add_enabled_ui(self.budget.use_ebn0, |ui| {
+                            egui::ComboBox::new("modulation", "")
+                                .selected_text(self.modulation.to_string())
+                                .show_ui(ui, |ui| {
+                                    for modulation in [Modulation::Custom, Modulation::Bpsk, Modulation::Qpsk, Modulation::Qam16] {
+                                        let response = ui.selectable_value(&mut self.modulation, modulation, modulation.to_string());
+                                        if response.clicked() {
+                                            if let Some(ebn0) = modulation.required_ebn0() {
+                                                self.budget.required_ebn0 = ebn0;
+                                            }
+                                        }
+                                    }
+                                })
+                                .response
+                                .on_hover_text("Eb/N0 for a bit error rate of 1e-6 without coding");
+                        });
+                        ui.label("");
+                        ui.end_row();
+
+                        ui.label("Eb/N0");
+                        ui.label(format!("{:.precision$}", calc::snr_to_ebn0(self.budget.snr, self.budget.data_rate, self.budget.bandwidth)));
+                        ui.label("dB");
+                        ui.end_row();
+                    }
+
+                    let capacity = calc::shannon_capacity(self.budget.bandwidth, self.budget.snr);
+                    ui.label("Capacity")
+                        .on_hover_text("Shannon limit of the data rate at this SNR and bandwidth");
+                    ui.label(format_data_rate(capacity));
+                    ui.label("");
+                    ui.end_row();
+
+                    target_selector(ui, &mut self.budget.target, Target::TxPower, "Tx Power");
+                    let mut tx_power = Power { val_dbm: self.budget.tx_power, unit: self.tx_power_unit.clone() };
+                    ui.horizontal(|ui| {
+                        ui.add_enabled_ui(self.budget.target != Target::TxPower, |ui| {
+                            tx_power.value_selector_ui(ui);
+                        });
+                        tx_power.unit_selector_ui("tx_power", ui);
+                    });
+                    (self.budget.tx_power, self.tx_power_unit) = (tx_power.val_dbm, tx_power.unit);
+                    ui.end_row();
+
+                    ui.label("EIRP")
+                        .on_hover_text("Tx power plus the gains and minus the losses marked as Tx");
+                    self.eirp.val_dbm = self.budget.eirp();
+                    ui.label(format!("{:.precision$}", self.eirp.get_in_unit()));
+                    self.eirp.unit_selector_ui("eirp", ui);
+                    ui.end_row();
+
+                    ui.label("Rx Power")
+                        .on_hover_text("Power at the receiver, noise floor plus SNR");
+                    self.rx_power.val_dbm = self.budget.snr + thermal_noise_floor;
+                    ui.label(format!("{:.precision$}", self.rx_power.get_in_unit()));
+                    self.rx_power.unit_selector_ui("rx_power", ui);
+                    ui.end_row();
+
+                    let field_strength = calc::dbm_to_field_strength(self.budget.eirp(), 0.0, self.budget.distance);
+                    ui.label("Field strength")
+                        .on_hover_text(format!("At the Rx in free space, {field_strength:.3e} V/m"));
+                    ui.label(format!("{:.precision$}", 20.0 * f64::log10(field_strength * 1e6)));
+                    ui.label("dBµV/m");
+                })
+            });
+        });
+    }
+
+    fn ui_path_loss(&mut self, ui: &mut Ui) {
+        let precision = self.precision;
+        frame_styled(&ui).show(ui, |ui| {
+            ui.vertical(|ui| {
+                ui.heading("Path loss");
+                egui::Grid::new("path_loss").show(ui, |ui| {
+                    target_selector(ui, &mut self.budget.target, Target::Distance, "Distance");
+                    let slant_path = self.budget.slant_elevation().is_some();
+                    let mut distance = Length { val_m: self.budget.distance, unit: self.distance_unit };
+                    ui.add_enabled_ui(self.budget.target != Target::Distance && !slant_path, |ui| {
+                        distance.value_selector_ui(ui, self.step_scale);
+                    });
+                    distance.unit_selector_ui("distance_unit", ui);
+                    (self.budget.distance, self.distance_unit) = (distance.val_m, distance.unit);
+                    ui.end_row();
+
+                    ui.add_enabled(
+                        self.budget.target != Target::Distance,
+                        egui::Checkbox::new(&mut self.budget.slant_path, "Slant path"),
+                    )
+                    .on_hover_text("Distance to a satellite from its altitude and the elevation angle")
+                    .on_disabled_hover_text("The distance is solved");
+                    ui.end_row();
+                    if slant_path {
+                        ui.label("Altitude");
+                        ui.add(DragValue::new(&mut self.budget.orbit_altitude).range(0.0..=f64::MAX).suffix(" km"));
+                        ui.label("");
+                        ui.end_row();
+
+                        ui.label("Elevation");
+                        ui.add(DragValue::new(&mut self.budget.elevation).range(0.0..=90.0).speed(0.1).suffix("°"));
+                        ui.label("");
+                        ui.end_row();
+                    }
+                    if let Some(far_field) = self.budget.far_field_distance().filter(|d| self.budget.distance < *d) {
+                        ui.label("");
+                        ui.colored_label(ui.visuals().warn_fg_color, format!("near field, < {}", format_distance(far_field)))
+                            .on_hover_text("Inside the Fraunhofer distance 2 D² / λ of the largest antenna aperture the far field models are unreliable");
+                        ui.end_row();
+                    }
+
+                    let delay = calc::propagation_delay(self.budget.distance, self.budget.physics);
+                    ui.label("Delay")
+                        .on_hover_text("Propagation time t = d / c");
+                    ui.label(format!("{} one way", format_duration(delay)));
+                    ui.label(format!("{} round trip", format_duration(2.0 * delay)));
+                    ui.end_row();
+
+                    ui.label("Model")
+                        .on_hover_text("Friis: free space up to the break distance. Two ray: direct and ground reflected ray.");
+                    egui::ComboBox::new("propagation_model", "")
+                        .selected_text(self.budget.propagation_model.to_string())
+                        .show_ui(ui, |ui| {
+                            for model in [PropagationModel::Friis, PropagationModel::TwoRay] {
+                                ui.selectable_value(&mut self.budget.propagation_model, model, model.to_string());
+                            }
+                        });
+                    ui.end_row();
+
+                    match self.budget.propagation_model {
+                        PropagationModel::Friis => {
+                            ui.label("break distance")
+                                .on_hover_text("Distance where the loss changes from the free space slope to the break exponent");
+                            let mut d_break = Length { val_m: self.budget.d_break, unit: self.d_break_unit };
+                            ui.add_enabled_ui(!self.budget.break_at_far_field, |ui| {
+                                d_break.value_selector_ui(ui, self.step_scale);
+                            });
+                            d_break.unit_selector_ui("d_break_unit", ui);
+                            (self.budget.d_break, self.d_break_unit) = (d_break.val_m, d_break.unit);
+                            ui.end_row();
+                            if self.budget.d_break < calc::friis::MIN_D_BREAK {
+                                ui.label("");
+                                ui.colored_label(
+                                    ui.visuals().warn_fg_color,
+                                    format!("calculated with {} mm", calc::friis::MIN_D_BREAK * 1e3),
+                                );
+                                ui.end_row();
+                            }
+
+                            ui.checkbox(&mut self.budget.break_at_far_field, "far field of")
+                                .on_hover_text("Break at the Fraunhofer distance 2 D² / λ of the antenna aperture D");
+                            ui.add_enabled(
+                                self.budget.break_at_far_field,
+                                DragValue::new(&mut self.budget.break_aperture)
+                                    .range(0.0..=f64::MAX)
+                                    .speed(0.01)
+                                    .suffix(" m"),
+                            );
+                            ui.end_row();
+
+                            ui.label("break exponent").on_hover_text(
+                                "Two slope model: the loss grows with 20 dB per decade of distance up to the \
+                                 break and with 10 n dB per decade beyond it. n is 2 in free space, about 4 \
+                                 over flat ground and up to 6 in dense clutter.",
+                            );
+                            stepper(ui, &mut self.budget.break_exponent, 0.1 * self.step_scale, "");
+                            self.budget.break_exponent = self
+                                .break_exponent
+                                .clamp(calc::friis::MIN_BREAK_EXPONENT, calc::friis::MAX_BREAK_EXPONENT);
+                            ui.end_row();
+                            ui.label("");
+                            ui.weak(format!("{:.0} dB/decade beyond the break", 10.0 * self.budget.break_exponent));
+                            ui.end_row();
+
+                            ui.label("1 m, 1 GHz loss")
+                                .on_hover_text("Reference of the model, the free space loss at 1 m and 1 GHz");
+                            ui.horizontal(|ui| {
+                                ui.add(DragValue::new(&mut self.budget.reference_loss).speed(0.01).suffix(" dB"));
+                                if ui.button("free space").clicked() {
+                                    self.budget.reference_loss = calc::friis::free_space_reference(self.budget.physics);
+                                }
+                            });
+                            ui.end_row();
+                        }
+                        PropagationModel::TwoRay => {
+                            ui.label("Tx height")
+                                .on_hover_text("Antenna height above the reflecting ground, beyond 4 h_tx h_rx / λ the loss grows with 40 log10(d)");
+                            ui.add(DragValue::new(&mut self.budget.tx_height).range(0.0..=f64::MAX).suffix(" m"));
+                            ui.end_row();
+
+                            ui.label("Rx height")
+                                .on_hover_text("Antenna height above the reflecting ground");
+                            ui.add(DragValue::new(&mut self.budget.rx_height).range(0.0..=f64::MAX).suffix(" m"));
+                            ui.end_row();
+                        }
+                    }
+
+                    let path_loss = self.budget.path_loss();
+                    ui.label("Path Loss")
+                        .on_hover_text("Free space FSPL = 20 log10(4 π d / λ) up to the break");
+                    if self.path_loss_as_gain {
+                        ui.label(format!("{:.precision$} dB", -path_loss));
+                    } else {
+                        ui.label(format!("{path_loss:.precision$} dB"));
+                    }
+                    ui.checkbox(&mut self.path_loss_as_gain, "as gain")
+                        .on_hover_text("Show the path loss as a negative gain");
+                    ui.end_row();
+
+                    let midpath = self.budget.distance / 2.0;
+                    let fresnel_radius = calc::fresnel_radius(1, midpath, midpath, self.budget.frequency, self.budget.physics);
+                    ui.label("Fresnel zone")
+                        .on_hover_text("Radius of the first Fresnel zone at midpath");
+                    ui.label(format!("{fresnel_radius:.2} m")).on_hover_text(format!(
+                        "Keep 60 % ({:.2} m) clear of obstacles for free space propagation",
+                        0.6 * fresnel_radius,
+                    ));
+                    ui.end_row();
+
+                    ui.checkbox(&mut self.budget.obstacle, "Obstacle")
+                        .on_hover_text("Single knife edge, like a ridgeline, between Tx and Rx");
+                    ui.add_enabled(
+                        self.budget.obstacle,
+                        DragValue::new(&mut self.budget.obstacle_height).speed(0.1).suffix(" m"),
+                    )
+                    .on_hover_text("Height above the line of sight, negative below");
+                    ui.end_row();
+
+                    if self.budget.obstacle {
+                        ui.label("from Tx");
+                        ui.add(
+                            DragValue::new(&mut self.budget.obstacle_distance)
+                                .range(0.0..=f64::MAX)
+                                .suffix(" m"),
+                        );
+                        ui.end_row();
+
+                        ui.label("Diffraction loss");
+                        if self.budget.obstacle_distance < self.budget.distance {
+                            ui.label(format!("{:.precision$} dB", self.budget.diffraction_loss()));
+                        } else {
+                            ui.colored_label(ui.visuals().warn_fg_color, "beyond the Rx");
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+    }
+
+    /// Categories of the gains with the sum of their gains in dB, in the order of their first entry
+    fn gain_categories(&self) -> Vec<(String, f64)> {
+        let mut categories: Vec<(String, f64)> = Vec::new();
+        for gain in self.budget.gains.values() {
+            let value = if gain.enabled { gain.value_at(self.budget.frequency) } else { 0.0 };
+            match categories.iter_mut().find(|(category, _)| *category == gain.category) {
+                Some((_, subtotal)) => { *subtotal += value; }
+                None => { categories.push((gain.category.clone(), value)); }
+            }
+        }
+        categories
+    }
+
+    /// Categories of the losses with the sum of their losses in dB in the current condition, in
+    /// the order of their first entry
+    fn loss_categories(&self) -> Vec<(String, f64)> {
+        let mut categories: Vec<(String, f64)> = Vec::new();
+        for loss in self.budget.losses.values() {
+            let value = if loss.applies(self.budget.condition) { loss.value_at(self.budget.frequency) } else { 0.0 };
+            match categories.iter_mut().find(|(category, _)| *category == loss.category) {
+                Some((_, subtotal)) => { *subtotal += value; }
+                None => { categories.push((loss.category.clone(), value)); }
+            }
+        }
+        categories
+    }
+
+    fn ui_gain_table(&mut self, ui: &mut Ui, category: &str) {
+        let precision = self.precision;
+        TableBuilder::new(ui)
+            .id_salt(("gain_table", category))
+            .striped(true)
+            .column(Column::exact(20.0))
+            .column(Column::exact(20.0))
+            .column(Column::exact(100.0))
+            .column(Column::exact(70.0))
+            .column(Column::exact(100.0))
+            .column(Column::exact(110.0))
+            .column(Column::exact(30.0))
+            .header(20., |mut header| {
+                header.col(|ui| {
+                    ui.label(" ");
+                });
+                header.col(|ui| {
+                    ui.label(" ");
+                });
+                header.col(|ui| {
+                    ui.heading("Name");
+                });
+                header.col(|ui| {
+                    ui.heading("Category");
+                });
+                header.col(|ui| {
+                    ui.heading("Value");
+                });
+                header.col(|ui| {
+                    ui.heading("Scaling");
+                });
+                header.col(|ui| {
+                    ui.heading("Tx");
+                });
+            })
+            .body(|mut body| {
+                let frequency = self.budget.frequency;
+                let mut rename = None;
+                self.budget.gains.retain(|name, gain| {
+                    if gain.category != category {
+                        return true;
+                    }
+                    let mut retain = true;
+                    body.row(20.0, |mut row| {
+                        row.col(|ui| {
+                            if ui.button("X").clicked() {
+                                retain = false;
+                            }
+                        });
+                        row.col(|ui| {
+                            ui.checkbox(&mut gain.enabled, "")
+                                .on_hover_text("Count this gain in the budget");
+                        });
+                        row.col(|ui| {
+                            if let Some(text) = deferred_text_edit(ui, ("gain_name", name.as_str()), name, "") {
+                                rename = Some((name.clone(), text));
+                            }
+                        });
+                        row.col(|ui| {
+                            if let Some(text) = deferred_text_edit(ui, ("gain_category", name.as_str()), &gain.category, "none") {
+                                gain.category = text.trim().to_string();
+                            }
+                        });
+                        row.col(|ui| match &mut gain.pattern {
+                            Some(pattern) => {
+                                let value = calc::antenna::interpolate_gain(&pattern.table, pattern.angle);
+                                ui.add(DragValue::new(&mut pattern.angle).speed(0.5).suffix("°"))
+                                    .on_hover_text(format!("Pointing angle, {value:.precision$} dBi from the pattern"));
+                            }
+                            None => {
+                                ratio_ui(ui, &mut gain.value, &mut gain.unit, calc::validate_gain);
+                            }
+                        });
+                        row.col(|ui| {
+                            ui.add_enabled_ui(gain.pattern.is_none(), |ui| {
+                                scaling_ui(
+                                    ui,
+                                    ("gain_scaling", name.as_str()),
+                                    &mut gain.scaling,
+                                    &mut gain.reference_frequency,
+                                    &mut gain.slope,
+                                    frequency,
+                                );
+                            });
+                        });
+                        row.col(|ui| {
+                            ui.checkbox(&mut gain.tx_antenna, "")
+                                .on_hover_text("Tx antenna gain, counts towards the EIRP");
+                        });
+                    });
+                    retain
+                });
+                if let Some((old_name, new_name)) = rename {
+                    rename_entry(&mut self.budget.gains, &old_name, &new_name);
+                }
+            });
+    }
+
+    fn ui_loss_table(&mut self, ui: &mut Ui, category: &str) {
+        let precision = self.precision;
+        TableBuilder::new(ui)
+            .id_salt(("loss_table", category))
+            .striped(true)
+            .column(Column::exact(20.0))
+            .column(Column::exact(20.0))
+            .column(Column::exact(90.0))
+            .column(Column::exact(60.0))
+            .column(Column::exact(100.0))
+            .column(Column::exact(50.0))
+            .column(Column::exact(110.0))
+            .column(Column::exact(30.0))
+            .header(20., |mut header| {
+                header.col(|ui| {
+                    ui.label(" ");
+                });
+                header.col(|ui| {
+                    ui.label(" ");
+                });
+                header.col(|ui| {
+                    ui.heading("Name");
+                });
+                header.col(|ui| {
+                    ui.heading("Category");
+                });
+                header.col(|ui| {
+                    ui.heading("Value");
+                });
+                header.col(|ui| {
+                    ui.heading("Fade");
+                });
+                header.col(|ui| {
+                    ui.heading("Scaling");
+                });
+                header.col(|ui| {
+                    ui.heading("Tx");
+                });
+            })
+            .body(|mut body| {
+                let frequency = self.budget.frequency;
+                let mut rename = None;
+                self.budget.losses.retain(|name, loss| {
+                    if loss.category != category {
+                        return true;
+                    }
+                    let mut retain = true;
+                    body.row(20.0, |mut row| {
+                        row.col(|ui| {
+                            if ui.button("X").clicked() {
+                                retain = false;
+                            }
+                        });
+                        row.col(|ui| {
+                            ui.checkbox(&mut loss.enabled, "")
+                                .on_hover_text("Count this loss in the budget");
+                        });
+                        row.col(|ui| {
+                            if let Some(text) = deferred_text_edit(ui, ("loss_name", name.as_str()), name, "") {
+                                rename = Some((name.clone(), text));
+                            }
+                        });
+                        row.col(|ui| {
+                            if let Some(text) = deferred_text_edit(ui, ("loss_category", name.as_str()), &loss.category, "none") {
+                                loss.category = text.trim().to_string();
+                            }
+                        });
+                        row.col(|ui| match &mut loss.cable_run {
+                            Some(run) => {
+                                let value = run.cable.loss(run.length, frequency);
+                                ui.add(DragValue::new(&mut run.length).range(0.0..=f64::MAX).speed(0.1).suffix(" m"))
+                                    .on_hover_text(format!(
+                                        "{} cable, {value:.precision$} dB at {}",
+                                        run.cable.name,
+                                        format_frequency(frequency),
+                                    ));
+                            }
+                            None => {
+                                ratio_ui(ui, &mut loss.value, &mut loss.unit, calc::validate_loss);
+                            }
+                        });
+                        row.col(|ui| {
+                            let mut faded_only = loss.condition == LossCondition::FadedOnly;
+                            if ui.checkbox(&mut faded_only, "")
+                                .on_hover_text("Only applies in the faded condition")
+                                .changed()
+                            {
+                                loss.condition = if faded_only {
+                                    LossCondition::FadedOnly
+                                } else {
+                                    LossCondition::Always
+                                };
+                            }
+                        });
+                        row.col(|ui| {
+                            ui.add_enabled_ui(loss.cable_run.is_none(), |ui| {
+                                scaling_ui(
+                                    ui,
+                                    ("loss_scaling", name.as_str()),
+                                    &mut loss.scaling,
+                                    &mut loss.reference_frequency,
+                                    &mut loss.slope,
+                                    frequency,
+                                );
+                            });
+                        });
+                        row.col(|ui| {
+                            ui.checkbox(&mut loss.tx_feed, "")
+                                .on_hover_text("Tx feed loss, counts towards the EIRP");
+                        });
+                    });
+                    retain
+                });
+                if let Some((old_name, new_name)) = rename {
+                    rename_entry(&mut self.budget.losses, &old_name, &new_name);
+                }
+            });
+    }
+
+    /// Calculate the gain of a dish and insert it as a gain named after the name field
+    fn ui_dish_gain(&mut self, ui: &mut Ui) {
+        let precision = self.precision;
+        let gain = calc::antenna::parabolic_gain(self.dish_diameter, self.budget.frequency, self.dish_efficiency, self.budget.physics);
+        egui::Grid::new("dish_gain").num_columns(2).show(ui, |ui| {
+            ui.label("Diameter");
+            ui.add(DragValue::new(&mut self.dish_diameter).range(0.0..=f64::MAX).speed(0.01).suffix(" m"));
+            ui.end_row();
+
+            ui.label("Efficiency");
+            ui.add(DragValue::new(&mut self.dish_efficiency).range(0.0..=1.0).speed(0.01));
+            ui.end_row();
+
+            ui.label("Gain");
+            ui.label(format!("{gain:.precision$} dBi at {}", format_frequency(self.budget.frequency)));
+            ui.end_row();
+        });
+        if ui.add_enabled(gain.is_finite(), egui::Button::new("Insert")).clicked() {
+            let name = if self.gain_name.trim().is_empty() {
+                format!("Dish {} m", self.dish_diameter)
+            } else {
+                std::mem::take(&mut self.gain_name)
+            };
+            self.budget.gains.insert(name, Gain::new(gain));
+            ui.memory_mut(|memory| memory.close_popup());
+        }
+    }
+
+    /// Calculate the gain from the half power beamwidths and insert it as a gain named after the
+    /// name field
+    fn ui_beamwidth_gain(&mut self, ui: &mut Ui) {
+        let precision = self.precision;
+        let gain = calc::antenna::gain_from_beamwidth(self.beamwidth_azimuth, self.beamwidth_elevation);
+        egui::Grid::new("beamwidth_gain").num_columns(2).show(ui, |ui| {
+            ui.label("Azimuth");
+            ui.add(DragValue::new(&mut self.beamwidth_azimuth).range(0.1..=360.0).speed(0.1).suffix("°"));
+            ui.end_row();
+
+            ui.label("Elevation");
+            ui.add(DragValue::new(&mut self.beamwidth_elevation).range(0.1..=180.0).speed(0.1).suffix("°"));
+            ui.end_row();
+
+            ui.label("Gain")
+                .on_hover_text(format!("G = {} / (θ_az θ_el)", calc::antenna::BEAMWIDTH_PRODUCT));
+            ui.label(format!("{gain:.precision$} dBi"));
+            ui.end_row();
+
+            ui.label("Beamwidth of")
+                .on_hover_text("Half power beamwidth of a symmetric beam with the gain of the gain field");
+            ui.label(format!(
+                "{:.1}° at {} dBi",
+                calc::antenna::beamwidth_from_gain(self.gain_value),
+                self.gain_value,
+            ));
+            ui.end_row();
+        });
+        if ui.add_enabled(gain.is_finite(), egui::Button::new("Insert")).clicked() {
+            let name = if self.gain_name.trim().is_empty() {
+                format!("Antenna {}° x {}°", self.beamwidth_azimuth, self.beamwidth_elevation)
+            } else {
+                std::mem::take(&mut self.gain_name)
+            };
+            self.budget.gains.insert(name, Gain::new(gain));
+            ui.memory_mut(|memory| memory.close_popup());
+        }
+    }
+
+    fn ui_antennas(&mut self, ui: &mut Ui) {
+        let precision = self.precision;
+        frame_styled(ui).show(ui, |ui| {
+            ui.heading("Aperture Antennas");
+            ui.horizontal(|ui| {
+                let name_response =
+                    ui.add(TextEdit::singleline(&mut self.antenna_name).hint_text("Antenna Name"));
+                if ui.button("Add").clicked()
+                    || (name_response.lost_focus()
+                    && ui.input(|i| i.key_pressed(egui::Key::Enter)))
+                {
+                    if !self.antenna_name.trim().is_empty() {
+                        self.budget.antennas.insert(self.antenna_name.clone(), ApertureAntenna::default());
+                        self.antenna_name.clear();
+                    }
+                }
+            });
+            let (frequency, physics) = (self.budget.frequency, self.budget.physics);
+            TableBuilder::new(ui)
+                .id_salt("antenna_table")
+                .striped(true)
+                .column(Column::exact(20.0))
+                .column(Column::exact(120.0))
+                .column(Column::exact(80.0))
+                .column(Column::exact(70.0))
+                .column(Column::exact(70.0))
+                .header(20., |mut header| {
+                    header.col(|ui| {
+                        ui.label(" ");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Name");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Diameter");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Efficiency");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Gain");
+                    });
+                })
+                .body(|mut body| {
+                    self.budget.antennas.retain(|name, antenna| {
+                        let mut retain = true;
+                        body.row(20.0, |mut row| {
+                            row.col(|ui| {
+                                if ui.button("X").clicked() {
+                                    retain = false;
+                                }
+                            });
+                            row.col(|ui| {
+                                ui.label(name.as_str());
+                            });
+                            row.col(|ui| {
+                                ui.add(
+                                    DragValue::new(&mut antenna.diameter)
+                                        .range(0.0..=f64::MAX)
+                                        .speed(0.01)
+                                        .suffix(" m"),
+                                );
+                            });
+                            row.col(|ui| {
+                                ui.add(
+                                    DragValue::new(&mut antenna.efficiency)
+                                        .range(0.0..=1.0)
+                                        .speed(0.01),
+                                );
+                            });
+                            row.col(|ui| {
+                                ui.label(format!("{:.precision$} dBi", antenna.gain(frequency, physics)));
+                            });
+                        });
+                        retain
+                    });
+                });
+        });
+    }
+
+    fn ui_noise_cascade(&mut self, ui: &mut Ui) {
+        let precision = self.precision;
+        ui.collapsing("Noise figure cascade", |ui| {
+            ui.checkbox(&mut self.budget.noise_cascade, "Use as the noise figure")
+                .on_hover_text("Friis formula for noise, the gain of the first stages suppresses the noise of the later ones");
+            egui::Grid::new("noise_stages").num_columns(4).striped(true).show(ui, |ui| {
+                ui.label(" ");
+                ui.strong("Stage");
+                ui.strong("Gain");
+                ui.strong("NF");
+                ui.end_row();
+
+                self.budget.noise_stages.retain_mut(|stage| {
+                    let mut retain = true;
+                    if ui.button("X").clicked() {
+                        retain = false;
+                    }
+                    ui.add(TextEdit::singleline(&mut stage.name).desired_width(100.0));
+                    ui.add(DragValue::new(&mut stage.gain).speed(0.1).suffix(" dB"));
+                    ui.add(DragValue::new(&mut stage.noise_figure).range(0.0..=f64::MAX).speed(0.1).suffix(" dB"));
+                    ui.end_row();
+                    retain
+                });
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Add").clicked() {
+                    self.budget.noise_stages.push(NoiseStage::new("Stage", 0.0, 0.0));
+                }
+                ui.label(format!("System noise figure {:.precision$} dB", self.budget.cascade_noise_figure()));
+            });
+        });
+    }
+
+    fn ui_receiver(&mut self, ui: &mut Ui) {
+        let precision = self.precision;
+        frame_styled(ui).show(ui, |ui| {
+            ui.vertical(|ui| {
+                ui.heading("Receiver G/T");
+                egui::Grid::new("receiver").num_columns(3).show(ui, |ui| {
+                    ui.label("Antenna gain");
+                    ui.add(DragValue::new(&mut self.budget.rx_antenna_gain).speed(0.1));
+                    ui.label("dBi");
+                    ui.end_row();
+
+                    ui.checkbox(&mut self.budget.noise_chain, "Noise chain")
+                        .on_hover_text("Refer the noise of antenna, feedline and LNA to the antenna terminal instead of using the temperature and noise figure of the parameters");
+                    ui.end_row();
+
+                    if self.budget.noise_chain {
+                        ui.label("Antenna temperature");
+                        ui.add(DragValue::new(&mut self.budget.antenna_temperature).range(0.0..=f64::MAX));
+                        ui.label("K");
+                        ui.end_row();
+
+                        ui.label("Feedline loss");
+                        ui.add(DragValue::new(&mut self.budget.feedline_loss).range(0.0..=f64::MAX).speed(0.01));
+                        ui.label("dB");
+                        ui.end_row();
+
+                        ui.label("Feedline temperature");
+                        ui.add(DragValue::new(&mut self.budget.feedline_temperature).range(0.0..=f64::MAX));
+                        ui.label("K");
+                        ui.end_row();
+
+                        ui.label("LNA noise figure");
+                        ui.add(DragValue::new(&mut self.budget.lna_noise_figure).range(0.0..=f64::MAX).speed(0.01));
+                        ui.label("dB");
+                        ui.end_row();
+                    }
+
+                    ui.label("System temperature");
+                    ui.label(format!("{:.precision$}", self.budget.system_temperature()));
+                    ui.label("K");
+                    ui.end_row();
+
+                    ui.label("G/T");
+                    ui.label(format!("{:.precision$}", self.budget.g_over_t()));
+                    ui.label("dB/K");
+                    ui.end_row();
+                });
+            });
+        });
+    }
+
+    /// Link margin at each of the checkpoint distances, None for entries that are not a number
+    pub fn checkpoint_margins(&self) -> Vec<(String, Option<(f64, f64)>)> {
+        self.checkpoint_distances
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let result = parse_prefixed(entry)
+                    .map(|distance| (distance, self.budget.margin_at(self.budget.frequency, distance)));
+                (entry.to_string(), result)
+            })
+            .collect()
+    }
+
+    fn ui_checkpoints(&mut self, ui: &mut Ui) {
+        let precision = self.precision;
+        frame_styled(ui).show(ui, |ui| {
+            ui.heading("Distance Checkpoints");
+            ui.add(
+                TextEdit::singleline(&mut self.checkpoint_distances)
+                    .hint_text("100, 500, 1k, 5k")
+                    .desired_width(300.0),
+            )
+            .on_hover_text("Comma separated distances in meter");
+            let checkpoints = self.checkpoint_margins();
+            egui::Grid::new("checkpoints").num_columns(3).striped(true).show(ui, |ui| {
+                ui.strong("Distance");
+                ui.strong("Margin");
+                ui.strong("Link");
+                ui.end_row();
+                for (entry, result) in &checkpoints {
+                    match result {
+                        Some((distance, margin)) => {
+                            ui.label(format_distance(*distance));
+                            ui.label(format!("{margin:+.precision$} dB"));
+                            if *margin >= 0.0 {
+                                ui.label("✔");
+                            } else {
+                                ui.colored_label(ui.visuals().error_fg_color, "✖");
+                            }
+                        }
+                        None => {
+                            ui.label(entry.as_str());
+                            ui.colored_label(ui.visuals().error_fg_color, "not a distance");
+                            ui.label("");
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+            if ui.button("Export data CSV").clicked() {
+                let rows: Vec<Vec<String>> = checkpoints
+                    .iter()
+                    .filter_map(|(_, result)| *result)
+                    .map(|(distance, margin)| {
+                        vec![distance.to_string(), margin.to_string(), (margin >= 0.0).to_string()]
+                    })
+                    .collect();
+                save_csv(ui.ctx(), "checkpoints.csv", export::series_to_csv(&["distance_m", "margin_db", "closes"], &rows));
+            }
+        });
+    }
+
+    fn ui_relays(&mut self, ui: &mut Ui) {
+        let precision = self.precision;
+        ui.collapsing("Relays", |ui| {
+            ui.label("The budget is the first hop, its receiver the first relay. Each relay amplifies what it receives, noise included, and forwards it over the next hop.");
+            let snrs = self.budget.hop_snrs();
+            egui::Grid::new("relays").num_columns(7).striped(true).show(ui, |ui| {
+                ui.label(" ");
+                ui.strong("Distance");
+                ui.strong("Relay gain");
+                ui.strong("Gains");
+                ui.strong("Losses");
+                ui.strong("Rx NF");
+                ui.strong("SNR").on_hover_text("Before the implementation margin, it is subtracted once from the end to end SNR");
+                ui.end_row();
+
+                ui.label(" ");
+                ui.label(format_distance(self.budget.distance));
+                ui.label("");
+                ui.label(format!("{:.precision$} dB", self.budget.total_gains()));
+                ui.label(format!("{:.precision$} dB", self.budget.total_losses()));
+                ui.label("");
+                ui.label(format!("{:.precision$} dB", snrs[0]));
+                ui.end_row();
+
+                let mut snrs = snrs.iter().skip(1);
+                self.budget.hops.retain_mut(|hop| {
+                    let mut retain = true;
+                    if ui.button("X").clicked() {
+                        retain = false;
+                    }
+                    ui.add(DragValue::new(&mut hop.distance).range(0.0..=f64::MAX).suffix(" m"));
+                    ui.add(DragValue::new(&mut hop.relay_gain).speed(0.1).suffix(" dB"));
+                    ui.add(DragValue::new(&mut hop.gains).speed(0.1).suffix(" dB"));
+                    ui.add(DragValue::new(&mut hop.losses).range(0.0..=f64::MAX).speed(0.1).suffix(" dB"));
+                    ui.add(DragValue::new(&mut hop.noise_figure).range(0.0..=f64::MAX).speed(0.1).suffix(" dB"));
+                    ui.label(format!("{:.precision$} dB", snrs.next().copied().unwrap_or(f64::NAN)));
+                    ui.end_row();
+                    retain
+                });
+            });
+            if ui.button("Add hop").clicked() {
+                self.budget.hops.push(Hop::default());
+            }
+            if !self.budget.hops.is_empty() {
+                let snr = self.budget.end_to_end_snr();
+                let margin = snr - self.budget.required_snr;
+                ui.label(format!("End to end SNR {snr:.precision$} dB, margin {margin:+.precision$} dB"));
+            }
+        });
+    }
+
+    fn ui_wizard_window(&mut self, ctx: &Context) {
+        let precision = self.precision;
+        let mut open = self.show_wizard;
+        egui::Window::new("Design Wizard")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Minimum EIRP and G/T to close a link over the given range, with the frequency, bandwidth, path model and losses of the budget.");
+                egui::Grid::new("wizard").num_columns(3).show(ui, |ui| {
+                    ui.label("Range");
+                    ui.add(DragValue::new(&mut self.wizard_range).range(0.0..=f64::MAX).suffix(" m"));
+                    ui.label("");
+                    ui.end_row();
+
+                    ui.label("Data rate");
+                    ui.add(
+                        prefix_drag_value(&mut self.wizard_data_rate, SiPrefix::Auto)
+                            .range(0.0..=f64::MAX)
+                            .speed(1e5),
+                    );
+                    ui.label("bit/s");
+                    ui.end_row();
+
+                    ui.label("Margin");
+                    ui.add(DragValue::new(&mut self.wizard_margin).speed(0.1));
+                    ui.label("dB");
+                    ui.end_row();
+
+                    let required_snr = calc::shannon_required_snr(self.wizard_data_rate, self.budget.bandwidth);
+                    if !required_snr.is_finite() {
+                        ui.label("");
+                        ui.colored_label(ui.visuals().error_fg_color, "Not achievable in this bandwidth");
+                        ui.end_row();
+                        return;
+                    }
+                    ui.label("Required SNR");
+                    ui.label(format!("{required_snr:.precision$}"));
+                    ui.label("dB");
+                    ui.end_row();
+
+                    let cn0 = required_snr + self.wizard_margin + 10.0 * f64::log10(self.budget.bandwidth);
+                    let path_loss = self.budget.path_loss_at(self.wizard_range, self.budget.d_break, self.budget.frequency);
+                    let losses = self.budget.total_losses() + self.budget.excess_loss_at(self.budget.frequency, self.wizard_range)
+                        + self.budget.implementation_margin;
+                    let eirp_plus_g_over_t = calc::required_eirp_plus_g_over_t(cn0, path_loss, losses, self.budget.physics);
+                    ui.label("EIRP + G/T");
+                    ui.label(format!("{eirp_plus_g_over_t:.precision$}"));
+                    ui.label("dBW/K");
+                    ui.end_row();
+
+                    let g_over_t = self.budget.g_over_t();
+                    ui.label("EIRP with current G/T");
+                    ui.label(format!("{:.precision$}", calc::dbw_to_dbm(eirp_plus_g_over_t - g_over_t)));
+                    ui.label("dBm");
+                    ui.end_row();
+                });
+            });
+        self.show_wizard = open;
+    }
+
+    fn ui_cable_loss_window(&mut self, ctx: &Context) {
+        let precision = self.precision;
+        let mut open = self.show_cable_loss;
+        egui::Window::new("Cable Loss")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if self.cable_types.is_empty() {
+                    ui.label("No cable types defined.");
+                    if ui.button("Edit cable types").clicked() {
+                        self.show_cable_types = true;
+                    }
+                    return;
+                }
+                self.cable_selected = self.cable_selected.min(self.cable_types.len() - 1);
+
+                egui::Grid::new("cable_loss").num_columns(2).show(ui, |ui| {
+                    ui.label("Cable");
+                    egui::ComboBox::new("cable_type", "")
+                        .selected_text(self.cable_types[self.cable_selected].name.as_str())
+                        .show_ui(ui, |ui| {
+                            for (i, cable) in self.cable_types.iter().enumerate() {
+                                ui.selectable_value(&mut self.cable_selected, i, cable.name.as_str());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Length");
+                    ui.add(
+                        DragValue::new(&mut self.cable_length)
+                            .range(0.0..=f64::MAX)
+                            .speed(0.1)
+                            .suffix(" m"),
+                    );
+                    ui.end_row();
+
+                    let cable = &self.cable_types[self.cable_selected];
+                    let loss = cable.loss(self.cable_length, self.budget.frequency);
+                    ui.label("Loss");
+                    ui.label(format!("{loss:.precision$} dB"));
+                    ui.end_row();
+                });
+
+                ui.horizontal(|ui| {
+                    if ui.button("Add to losses")
+                        .on_hover_text("Adds the cable run, its loss follows the frequency and the length")
+                        .clicked()
+                    {
+                        let cable = self.cable_types[self.cable_selected].clone();
+                        let name = format!("{} {} m", cable.name, self.cable_length);
+                        self.budget.losses.insert(name, Loss::cable(cable, self.cable_length, self.budget.frequency));
+                    }
+                    if ui.button("Edit cable types").clicked() {
+                        self.show_cable_types = true;
+                    }
+                });
+            });
+        self.show_cable_loss = open;
+    }
+
+    fn ui_cable_types_window(&mut self, ctx: &Context) {
+        let precision = self.precision;
+        let mut open = self.show_cable_types;
+        egui::Window::new("Cable Types")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new("cable_types").num_columns(5).striped(true).show(ui, |ui| {
+                    ui.label(" ");
+                    ui.label("Name");
+                    ui.label("Loss per 100 m");
+                    ui.label("at Frequency");
+                    ui.label("Exponent");
+                    ui.end_row();
+
+                    let frequency = self.budget.frequency;
+                    self.cable_types.retain_mut(|cable| {
+                        let mut retain = true;
+                        if ui.button("X").clicked() {
+                            retain = false;
+                        }
+                        ui.add(TextEdit::singleline(&mut cable.name).desired_width(100.0));
+                        if let Some(feeder) = cable.feeder {
+                            ui.label(format!("{:.precision$} dB", cable.loss(100.0, frequency)));
+                            ui.label(format_frequency(frequency));
+                            let cutoff = feeder.cutoff_frequency();
+                            let hover = if cutoff > 0.0 {
+                                format!("Copper waveguide, cutoff at {}", format_frequency(cutoff))
+                            } else {
+                                "Loss curve of the data sheet".to_string()
+                            };
+                            ui.label("catalog").on_hover_text(hover);
+                            ui.end_row();
+                            return retain;
+                        }
+                        ui.add(DragValue::new(&mut cable.loss_per_100m).range(0.0..=f64::MAX).suffix(" dB"));
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                prefix_drag_value(&mut cable.reference_frequency, SiPrefix::Auto)
+                                    .range(1.0..=f64::MAX)
+                                    .speed(1e6),
+                            );
+                            ui.label("Hz");
+                        });
+                        ui.add(DragValue::new(&mut cable.frequency_exponent).speed(0.01));
+                        ui.end_row();
+                        retain
+                    });
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Add").clicked() {
+                        self.cable_types.push(CableType::new("New cable", 10.0));
+                    }
+                    if ui.button("Restore defaults").clicked() {
+                        self.cable_types = default_cable_types();
+                    }
+                });
+            });
+        self.show_cable_types = open;
+    }
+}
+
+impl eframe::App for LinkBudgetApp {
+    fn save(&mut self, storage: &mut dyn Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, self);
     }
     fn update(&mut self, ctx: &Context, frame: &mut Frame) {
-        let total_db = self.total_sum();
+        self.store_theme(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.save_screenshot(ctx);
+        self.poll_monte_carlo();
+        self.nudge_with_keys(ctx);
+        self.undo_with_keys(ctx);
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 if ui.button("Reset").clicked() {
-                    *self = Self::default();
+                    self.show_reset = true;
+                }
+                if ui.add_enabled(!self.history.undo.is_empty(), egui::Button::new("Undo"))
+                    .on_hover_text("Ctrl+Z")
+                    .clicked()
+                {
+                    self.undo();
+                }
+                if ui.add_enabled(!self.history.redo.is_empty(), egui::Button::new("Redo"))
+                    .on_hover_text("Ctrl+Y")
+                    .clicked()
+                {
+                    self.redo();
+                }
+                ui.menu_button("Scenarios", |ui| {
+                    self.ui_scenarios_menu(ui);
+                });
+                ui.separator();
+                if ui.button("Cable Loss").clicked() {
+                    self.show_cable_loss = true;
+                }
+                if ui.button("Cable Types").clicked() {
+                    self.show_cable_types = true;
+                }
+                if ui.button("Design Wizard").clicked() {
+                    self.show_wizard = true;
+                }
+                if ui.button("Copy summary").on_hover_text("Copy the budget as text").clicked() {
+                    ui.ctx().copy_text(self.summary_text());
                 }
+                if ui.button("Export CSV").on_hover_text("Save every term of the budget as CSV").clicked() {
+                    self.export_csv(ui.ctx());
+                }
+                if ui.button("Export JSON").on_hover_text("Save the whole configuration to share it").clicked() {
+                    self.export_json(ui.ctx());
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                if ui.button("Export PNG").on_hover_text("Save a screenshot of the window as report").clicked() {
+                    ui.ctx().send_viewport_cmd(egui::ViewportCommand::Screenshot);
+                    self.screenshot_pending = true;
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                if ui.button("Import JSON").on_hover_text("Open a configuration saved with Export JSON").clicked() {
+                    self.import_json();
+                }
+                ui.separator();
+                let mut band_preset = None;
+                egui::ComboBox::new("band_preset", "")
+                    .selected_text(if self.band_preset.is_empty() { "Band preset" } else { self.band_preset.as_str() })
+                    .show_ui(ui, |ui| {
+                        if ui.selectable_label(self.band_preset.is_empty(), "None").clicked() {
+                            self.band_preset.clear();
+                        }
+                        for (name, preset) in BAND_PRESETS {
+                            if ui.selectable_label(self.band_preset == *name, *name)
+                                .on_hover_text(format!(
+                                    "{}, EIRP up to {} dBm",
+                                    format_frequency(preset.frequency),
+                                    preset.max_eirp,
+                                ))
+                                .clicked()
+                            {
+                                band_preset = Some(*name);
+                            }
+                        }
+                    });
+                if let Some(name) = band_preset {
+                    self.apply_band_preset(name);
+                }
+                ui.separator();
+                egui::ComboBox::new("constants", "")
+                    .selected_text(match self.budget.physics.constants {
+                        calc::Constants::Exact => "Exact constants",
+                        calc::Constants::Textbook => "Textbook constants",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.budget.physics.constants, calc::Constants::Exact, "Exact (CODATA)")
+                            .on_hover_text("k = 1.380649e-23 J/K, c = 299792458 m/s");
+                        ui.selectable_value(&mut self.budget.physics.constants, calc::Constants::Textbook, "Textbook")
+                            .on_hover_text("k = 1.38e-23 J/K, c = 3e8 m/s, results differ by a few hundredths of a dB");
+                    });
+                ui.separator();
+                ui.selectable_value(&mut self.view, View::Compact, "Compact");
+                ui.selectable_value(&mut self.view, View::Advanced, "Advanced");
+                ui.separator();
+                egui::ComboBox::new("step_scale", "")
+                    .selected_text(format!("Step ×{}", self.step_scale))
+                    .show_ui(ui, |ui| {
+                        for scale in [0.1, 1.0, 10.0] {
+                            ui.selectable_value(&mut self.step_scale, scale, format!("×{scale}"));
+                        }
+                    })
+                    .response
+                    .on_hover_text("Scales the step of the +/- buttons and arrow keys");
+                egui::ComboBox::new("precision", "")
+                    .selected_text(format!("{:.*} dB", self.precision, 0.0))
+                    .show_ui(ui, |ui| {
+                        for precision in 0..=4 {
+                            ui.selectable_value(&mut self.precision, precision, format!("{:.*} dB", precision, 0.0));
+                        }
+                    })
+                    .response
+                    .on_hover_text("Decimals of the displayed results");
+                let modifiers = ui.input(|i| i.modifiers);
+                let (name, step, unit) = self.nudged_parameter(modifiers);
+                // rounded, the scaled steps are not exact in binary
+                ui.label(format!("↑↓ {name} {} {unit}", (step * 1e6).round() / 1e6))
+                    .on_hover_text("The arrow keys move this input when no field is focused, Shift for coarse and Ctrl for fine steps");
                 ui.separator();
                 egui::widgets::global_theme_preference_buttons(ui);
             });
         });
+        self.ui_cable_loss_window(ctx);
+        self.ui_cable_types_window(ctx);
+        self.ui_wizard_window(ctx);
+        self.ui_import_error_window(ctx);
+        self.ui_reset_window(ctx);
+        self.ui_comparison_window(ctx);
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            self.ui_status_bar(ui);
+        });
+        let precision = self.precision;
         CentralPanel::default().show(ctx, |ui| {
-            ui.set_max_width(420.0);
-            ui.collapsing("How to use", |ui| {
-                ui.set_max_width(400.0);
-                ui.label("This tool calculates the link budget for a noise limited wireless transmission in free space. It can calculate the SNR, the required TX Power, or the achievable transmission distance. You can add Gains like TX or RX antenna gains, and losses like a fading margin or the noise figure.");
-                ui.label("Frequencies can be entered in scientific notation (20e6) or with a suffix (20M)");
-            });
-            ui.horizontal(|ui| {
-                self.ui_parameters(ui);
-                self.ui_path_loss(ui);
-            });
-            frame_styled(ui)
-                .show(ui, |ui| {
-                    ui.heading("Gains");
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.set_max_width(420.0);
+                ui.collapsing("How to use", |ui| {
+                    ui.set_max_width(400.0);
+                    ui.label("This tool calculates the link budget for a noise limited wireless transmission in free space. It can calculate the SNR, the required TX Power, or the achievable transmission distance. You can add Gains like TX or RX antenna gains, and losses like a fading margin or the noise figure.");
+                    ui.label("Frequencies can be entered in scientific notation (20e6) or with a suffix (20M)");
+                });
+                let verdict_color = if self.budget.link_margin() >= 0.0 {
+                    ui.visuals().widgets.noninteractive.fg_stroke.color
+                } else {
+                    ui.visuals().error_fg_color
+                };
+                let verdict_response = ui
+                    .add(
+                        egui::Label::new(egui::RichText::new(self.verdict()).color(verdict_color))
+                            .sense(egui::Sense::click()),
+                    )
+                    .on_hover_text("Click to explain the margin");
+                let popup_id = ui.make_persistent_id("margin_explanation");
+                if verdict_response.clicked() {
+                    ui.memory_mut(|memory| memory.toggle_popup(popup_id));
+                }
+                egui::popup_below_widget(
+                    ui,
+                    popup_id,
+                    &verdict_response,
+                    egui::PopupCloseBehavior::CloseOnClickOutside,
+                    |ui| {
+                        ui.set_min_width(350.0);
+                        self.ui_margin_explanation(ui);
+                    },
+                );
+                for warning in self.warnings() {
+                    ui.colored_label(ui.visuals().warn_fg_color, warning);
+                }
+                ui.horizontal(|ui| {
+                    self.ui_parameters(ui);
+                    self.ui_path_loss(ui);
+                });
+                frame_styled(ui)
+                    .show(ui, |ui| {
+                        ui.heading("Gains");
+                        ui.horizontal(|ui| {
+                            let name_response =
+                                ui.add(TextEdit::singleline(&mut self.gain_name).hint_text("Gain Name"));
+                            let value_response = ui.add(DragValue::new(&mut self.gain_value).suffix(" dB"));
+                            ui.add(TextEdit::singleline(&mut self.gain_category).hint_text("Category").desired_width(70.0));
+                            if ui.button("Add").clicked()
+                                || ((name_response.lost_focus() || value_response.lost_focus())
+                                && ui.input(|i| i.key_pressed(egui::Key::Enter)))
+                            {
+                                if !self.gain_name.trim().is_empty() {
+                                    let mut gain = Gain::new(self.gain_value);
+                                    gain.category = self.gain_category.trim().to_string();
+                                    self.budget.gains.insert(self.gain_name.clone(), gain);
+                                    self.gain_name.clear();
+                                }
+                            }
+                            let dish_response = ui.button("Dish…").on_hover_text("Gain of a parabolic dish");
+                            let popup_id = ui.make_persistent_id("dish_gain");
+                            if dish_response.clicked() {
+                                ui.memory_mut(|memory| memory.toggle_popup(popup_id));
+                            }
+                            egui::popup_below_widget(
+                                ui,
+                                popup_id,
+                                &dish_response,
+                                egui::PopupCloseBehavior::CloseOnClickOutside,
+                                |ui| {
+                                    ui.set_min_width(200.0);
+                                    self.ui_dish_gain(ui);
+                                },
+                            );
+                            let beamwidth_response = ui.button("Beamwidth…").on_hover_text("Gain from the half power beamwidths");
+                            let popup_id = ui.make_persistent_id("beamwidth_gain");
+                            if beamwidth_response.clicked() {
+                                ui.memory_mut(|memory| memory.toggle_popup(popup_id));
+                            }
+                            egui::popup_below_widget(
+                                ui,
+                                popup_id,
+                                &beamwidth_response,
+                                egui::PopupCloseBehavior::CloseOnClickOutside,
+                                |ui| {
+                                    ui.set_min_width(200.0);
+                                    self.ui_beamwidth_gain(ui);
+                                },
+                            );
+                            #[cfg(not(target_arch = "wasm32"))]
+                            if ui.button("Pattern CSV…")
+                                .on_hover_text("Gain from a table of angle and gain, evaluated at a pointing angle")
+                                .clicked()
+                            {
+                                self.import_pattern();
+                            }
+                        });
+                        for (category, subtotal) in self.gain_categories() {
+                            egui::CollapsingHeader::new(format!("{} ({subtotal:+.precision$} dB)", category_label(&category)))
+                                .id_salt(("gain_category", &category))
+                                .default_open(true)
+                                .show(ui, |ui| self.ui_gain_table(ui, &category));
+                        }
+                    });
+                if self.view == View::Advanced {
+                    self.ui_antennas(ui);
+                }
+                frame_styled(ui).show(ui, |ui| {
+                    ui.heading("Losses");
+                    ui.horizontal(|ui| {
+                        ui.label("Condition");
+                        ui.selectable_value(&mut self.budget.condition, Condition::Clear, "Clear sky");
+                        ui.selectable_value(&mut self.budget.condition, Condition::Faded, "Faded");
+                        ui.separator();
+                        ui.label(format!(
+                            "Margin clear {:.precision$} dB, faded {:.precision$} dB",
+                            self.budget.link_margin_in(Condition::Clear),
+                            self.budget.link_margin_in(Condition::Faded),
+                        ));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "Environmental losses clear {:.precision$} dB, faded {:.precision$} dB",
+                            self.budget.environmental_losses_in(Condition::Clear),
+                            self.budget.environmental_losses_in(Condition::Faded),
+                        ))
+                        .on_hover_text("Gases, rain, diffraction, polarization mismatch, fading margin and the faded only losses");
+                        ui.separator();
+                        ui.label(format!("Equipment losses {:.precision$} dB", self.budget.equipment_losses_in(self.budget.condition)));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Polarization");
+                        egui::ComboBox::new("polarization_mismatch", "")
+                            .selected_text(self.budget.polarization_mismatch.to_string())
+                            .show_ui(ui, |ui| {
+                                for mismatch in [
+                                    PolarizationMismatch::None,
+                                    PolarizationMismatch::LinearToLinear,
+                                    PolarizationMismatch::LinearToCircular,
+                                ] {
+                                    ui.selectable_value(&mut self.budget.polarization_mismatch, mismatch, mismatch.to_string());
+                                }
+                            })
+                            .response
+                            .on_hover_text(format!("Adds the mismatch as the loss \"{POLARIZATION_LOSS_NAME}\""));
+                        if self.budget.polarization_mismatch == PolarizationMismatch::LinearToLinear {
+                            ui.label("tilt");
+                            ui.add(DragValue::new(&mut self.budget.polarization_tilt).range(-90.0..=90.0).suffix("°"));
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Fading");
+                        egui::ComboBox::new("fading", "")
+                            .selected_text(self.budget.fading.to_string())
+                            .show_ui(ui, |ui| {
+                                for fading in [Fading::None, Fading::Rayleigh, Fading::Rician] {
+                                    ui.selectable_value(&mut self.budget.fading, fading, fading.to_string());
+                                }
+                            })
+                            .response
+                            .on_hover_text(format!("Adds the margin for the availability as the loss \"{FADING_MARGIN_NAME}\""));
+                        if self.budget.fading != Fading::None {
+                            ui.label("availability");
+                            ui.add(
+                                DragValue::new(&mut self.budget.availability)
+                                    .range(0.0..=99.9999)
+                                    .speed(0.01)
+                                    .max_decimals(4)
+                                    .suffix(" %"),
+                            );
+                        }
+                        if self.budget.fading == Fading::Rician {
+                            ui.label("K");
+                            ui.add(DragValue::new(&mut self.budget.k_factor).range(0.0..=100.0).speed(0.1))
+                                .on_hover_text("Ratio of the direct to the scattered power, linear");
+                        }
+                    });
                     ui.horizontal(|ui| {
                         let name_response =
-                            ui.add(TextEdit::singleline(&mut self.gain_name).hint_text("Gain Name"));
+                            ui.add(TextEdit::singleline(&mut self.loss_name).hint_text("Loss Name"));
+                        let value_response = ui.add(DragValue::new(&mut self.loss_value).suffix(" dB"));
+                        ui.add(TextEdit::singleline(&mut self.loss_category).hint_text("Category").desired_width(70.0));
                         if ui.button("Add").clicked()
-                            || (name_response.lost_focus()
+                            || ((name_response.lost_focus() || value_response.lost_focus())
                             && ui.input(|i| i.key_pressed(egui::Key::Enter)))
                         {
-                            if !self.gain_name.trim().is_empty() {
-                                self.gains.insert(self.gain_name.clone(), 10.0);
-                                self.gain_name.clear();
+                            if !self.loss_name.trim().is_empty() {
+                                let mut loss = Loss::new(self.loss_value);
+                                loss.category = self.loss_category.trim().to_string();
+                                self.budget.losses.insert(self.loss_name.clone(), loss);
+                                self.loss_name.clear();
                             }
                         }
-                    });
-                    TableBuilder::new(ui)
-                        .id_salt("gain_table")
-                        .striped(true)
-                        .column(Column::exact(20.0))
-                        .column(Column::exact(250.0))
-                        .column(Column::exact(100.0))
-                        .header(20., |mut header| {
-                            header.col(|ui| {
-                                ui.label(" ");
-                            });
-                            header.col(|ui| {
-                                ui.heading("Name");
-                            });
-                            header.col(|ui| {
-                                ui.heading("Value");
-                            });
+                        ui.menu_button("Common", |ui| {
+                            for (name, value) in calc::COMMON_LOSSES {
+                                let response = ui.add_enabled(
+                                    !self.budget.losses.contains_key(name),
+                                    egui::Button::new(format!("{name}: {value} dB")),
+                                );
+                                if response.clicked() {
+                                    self.budget.losses.insert(name.to_string(), Loss::new(value));
+                                    ui.close_menu();
+                                }
+                            }
                         })
-                        .body(|mut body| {
-                            self.gains.retain(|name, gain| {
-                                let mut retain = true;
-                                body.row(20.0, |mut row| {
-                                    row.col(|ui| {
-                                        if ui.button("X").clicked() {
-                                            retain = false;
-                                        }
-                                    });
-                                    row.col(|ui| {
-                                        ui.label(name.as_str());
-                                    });
-                                    row.col(|ui| {
-                                        ui.add(DragValue::new(gain).suffix(" dB"));
-                                    });
-                                });
-                                retain
-                            });
-                        });
-                });
-            frame_styled(ui).show(ui, |ui| {
-                ui.heading("Losses");
-                ui.horizontal(|ui| {
-                    let name_response =
-                        ui.add(TextEdit::singleline(&mut self.loss_name).hint_text("Loss Name"));
-                    if ui.button("Add").clicked()
-                        || (name_response.lost_focus()
-                        && ui.input(|i| i.key_pressed(egui::Key::Enter)))
-                    {
-                        if !self.loss_name.trim().is_empty() {
-                            self.losses.insert(self.loss_name.clone(), 10.0);
-                            self.loss_name.clear();
-                        }
+                        .response
+                        .on_hover_text("Insert a loss with a typical value");
+                    });
+                    for (category, subtotal) in self.loss_categories() {
+                        egui::CollapsingHeader::new(format!("{} ({:+.precision$} dB)", category_label(&category), -subtotal))
+                            .id_salt(("loss_category", &category))
+                            .default_open(true)
+                            .show(ui, |ui| self.ui_loss_table(ui, &category));
                     }
                 });
-                TableBuilder::new(ui)
-                    .id_salt("loss_table")
-                    .striped(true)
-                    .column(Column::exact(20.0))
-                    .column(Column::exact(250.0))
-                    .column(Column::exact(100.0))
-                    .header(20., |mut header| {
-                        header.col(|ui| {
-                            ui.label(" ");
-                        });
-                        header.col(|ui| {
-                            ui.heading("Name");
-                        });
-                        header.col(|ui| {
-                            ui.heading("Value");
-                        });
-                    })
-                    .body(|mut body| {
-                        self.losses.retain(|name, loss| {
-                            let mut retain = true;
-                            body.row(20.0, |mut row| {
-                                row.col(|ui| {
-                                    if ui.button("X").clicked() {
-                                        retain = false;
-                                    }
-                                });
-                                row.col(|ui| {
-                                    ui.label(name.as_str());
-                                });
-                                row.col(|ui| {
-                                    ui.add(DragValue::new(loss).suffix(" dB"));
-                                });
-                            });
-                            retain
-                        });
+                if self.view == View::Advanced {
+                    frame_styled(ui).show(ui, |ui| {
+                        self.ui_atmosphere(ui);
+                    });
+                    self.ui_receiver(ui);
+                    frame_styled(ui).show(ui, |ui| {
+                        self.ui_noise_cascade(ui);
+                    });
+                    self.ui_checkpoints(ui);
+                    frame_styled(ui).show(ui, |ui| {
+                        self.ui_relays(ui);
+                    });
+                    frame_styled(ui).show(ui, |ui| {
+                        self.ui_waterfall(ui);
+                    });
+                    frame_styled(ui).show(ui, |ui| {
+                        self.ui_sweeps(ui);
+                    });
+                    frame_styled(ui).show(ui, |ui| {
+                        self.ui_tolerances(ui);
                     });
+                    frame_styled(ui).show(ui, |ui| {
+                        self.ui_log(ui);
+                    });
+                }
             });
         });
 
-        if total_db.is_infinite() || total_db.is_nan() {
+        if !self.budget.solve().is_finite() {
             return;
         }
-
-        match self.calculation_target {
-            CalculationTarget::Snr => {
-                self.snr += total_db;
-            }
-            CalculationTarget::Distance => {
-                let new_path_loss = calc::friis::path_loss(self.distance, self.d_break, self.frequency, self.break_exponent) + total_db;
-                self.distance = calc::friis::distance(new_path_loss, self.d_break, self.frequency, self.break_exponent);
-            }
-            CalculationTarget::TxPower => {
-                self.tx_power.val_dbm -= total_db;
-            }
-        }
+        self.record_history(ctx);
+        self.log_target_change();
     }
 }
 
+/// Label of a value that can be solved for, selecting it makes the value the calculation target
+fn target_selector(ui: &mut Ui, current: &mut Target, target: Target, text: &str) -> Response {
+    let hover = if *current == target {
+        "Solved from the other values"
+    } else {
+        "Input, click to solve for it instead"
+    };
+    ui.selectable_value(current, target, text).on_hover_text(hover)
+}
+
 fn frame_styled(ui: &Ui) -> egui::Frame {
     egui::Frame::default()
         .stroke(ui.visuals().widgets.noninteractive.bg_stroke)
@@ -414,10 +3373,173 @@ fn frame_styled(ui: &Ui) -> egui::Frame {
         .inner_margin(5.0)
         .outer_margin(5.0)
 }
-fn prefix_drag_value(value: &mut f64) -> DragValue {
+/// DragValue with - and + buttons, the buttons and the arrow keys on the focused field change
+/// the value by step
+fn stepper(ui: &mut Ui, value: &mut f64, step: f64, suffix: &str) -> Response {
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 2.0;
+        let minus = ui.small_button("-");
+        let mut response = ui.add(DragValue::new(value).speed(step).suffix(suffix));
+        let plus = ui.small_button("+");
+        if minus.clicked() {
+            *value -= step;
+            response.mark_changed();
+        }
+        if plus.clicked() {
+            *value += step;
+            response.mark_changed();
+        }
+        response
+    })
+    .inner
+}
+
+/// Save CSV to a file, on the web where there is no file dialog it is copied to the clipboard
+fn save_csv(ctx: &Context, file_name: &str, csv: String) {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = ctx;
+        let file = rfd::FileDialog::new()
+            .set_file_name(file_name)
+            .add_filter("CSV", &["csv"])
+            .save_file();
+        if let Some(path) = file {
+            if let Err(e) = std::fs::write(&path, csv) {
+                log::error!("Failed to write {}: {e}", path.display());
+            }
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = file_name;
+        ctx.copy_text(csv);
+    }
+}
+
+fn format_frequency(frequency: f64) -> String {
+    match NumberPrefix::decimal(frequency) {
+        NumberPrefix::Standalone(num) => format!("{num:.0} Hz"),
+        NumberPrefix::Prefixed(prefix, num) => format!("{num:.1} {prefix}Hz"),
+    }
+}
+
+/// Antenna pattern from lines of angle in degree and gain in dBi, separated by a comma, a
+/// semicolon or whitespace. A header line is skipped. The table is sorted by angle.
+fn parse_pattern_csv(csv: &str) -> Result<Vec<(f64, f64)>, String> {
+    let mut table = Vec::new();
+    for (i, line) in csv.lines().enumerate() {
+        let fields: Vec<&str> = line
+            .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+            .filter(|field| !field.is_empty())
+            .collect();
+        if fields.is_empty() {
+            continue;
+        }
+        let point = match fields.as_slice() {
+            [angle, gain] => angle.parse::<f64>().ok().zip(gain.parse::<f64>().ok()),
+            _ => None,
+        };
+        match point {
+            Some((angle, gain)) if angle.is_finite() && gain.is_finite() => { table.push((angle, gain)); }
+            _ if i == 0 => {}
+            _ => { return Err(format!("Line {} is not an angle and a gain: {line}", i + 1)); }
+        }
+    }
+    if table.is_empty() {
+        return Err("The pattern has no angle and gain pairs".to_string());
+    }
+    table.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Ok(table)
+}
+
+/// Rename an entry keeping its value and position. An empty name or one that is already taken
+/// is rejected and false is returned.
+fn rename_entry<V>(map: &mut IndexMap<String, V>, old_name: &str, new_name: &str) -> bool {
+    let new_name = new_name.trim();
+    if new_name.is_empty() || map.contains_key(new_name) {
+        return false;
+    }
+    let Some((index, _, value)) = map.shift_remove_full(old_name) else {
+        return false;
+    };
+    map.shift_insert(index, new_name.to_string(), value);
+    true
+}
+
+/// Single line text edit that only returns the text once editing is finished, so that the
+/// value is not applied on every keystroke
+fn deferred_text_edit(ui: &mut Ui, id_salt: impl std::hash::Hash, value: &str, hint: &str) -> Option<String> {
+    let id = egui::Id::new(id_salt);
+    let mut text = ui.data_mut(|data| data.get_temp::<String>(id)).unwrap_or_else(|| value.to_string());
+    let response = ui.add(TextEdit::singleline(&mut text).id(id).hint_text(hint));
+    if response.lost_focus() {
+        ui.data_mut(|data| data.remove::<String>(id));
+        return (text != value).then_some(text);
+    }
+    if response.has_focus() {
+        ui.data_mut(|data| data.insert_temp(id, text));
+    }
+    None
+}
+
+/// Heading of a gain or loss category, entries without a category are listed as Other
+fn category_label(category: &str) -> &str {
+    if category.is_empty() { "Other" } else { category }
+}
+
+fn format_data_rate(data_rate: f64) -> String {
+    match NumberPrefix::decimal(data_rate) {
+        NumberPrefix::Standalone(num) => format!("{num:.0} bps"),
+        NumberPrefix::Prefixed(prefix, num) => format!("{num:.1} {prefix}bps"),
+    }
+}
+
+fn format_duration(seconds: f64) -> String {
+    if seconds < 1e-6 {
+        format!("{:.1} ns", seconds * 1e9)
+    } else if seconds < 1e-3 {
+        format!("{:.2} µs", seconds * 1e6)
+    } else if seconds < 1.0 {
+        format!("{:.2} ms", seconds * 1e3)
+    } else {
+        format!("{seconds:.2} s")
+    }
+}
+
+/// Date and time in UTC of seconds since the Unix epoch
+fn format_timestamp(timestamp: u64) -> String {
+    let (days, seconds) = ((timestamp / 86400) as i64, timestamp % 86400);
+    // civil from days, proleptic Gregorian calendar with eras of 400 years starting in March
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+    )
+}
+
+fn format_distance(distance: f64) -> String {
+    if distance >= 1000.0 {
+        format!("{:.1} km", distance / 1000.0)
+    } else {
+        format!("{distance:.0} m")
+    }
+}
+
+fn prefix_drag_value(value: &mut f64, pinned: SiPrefix) -> DragValue {
     DragValue::new(value)
-        .custom_formatter(
-            |value, range| match number_prefix::NumberPrefix::decimal(value) {
+        .custom_formatter(move |value, range| match pinned.factor() {
+            Some(factor) => format!("{:.3} {}", value / factor, pinned.symbol()),
+            None => match number_prefix::NumberPrefix::decimal(value) {
                 NumberPrefix::Standalone(num) => {
                     format!("{num} ")
                 }
@@ -425,29 +3547,277 @@ fn prefix_drag_value(value: &mut f64) -> DragValue {
                     format!("{:.1} {}", num, prefix)
                 }
             },
-        )
-        .custom_parser(|value| {
-            let Ok(number_prefix) = value.parse::<NumberPrefix<f64>>() else {
-                return None;
-            };
-
-            return match number_prefix {
-                NumberPrefix::Standalone(number) => Some(number),
-                NumberPrefix::Prefixed(prefix, number) => {
-                    let factor = match prefix {
-                        Prefix::Kilo => 1e3,
-                        Prefix::Mega => 1e6,
-                        Prefix::Giga => 1e9,
-                        Prefix::Tera => 1e12,
-                        Prefix::Peta => 1e15,
-                        Prefix::Exa => 1e18,
-                        Prefix::Zetta => 1e21,
-                        Prefix::Yotta => 1e24,
-                        _ => return None,
-                    };
-
-                    Some(factor * number)
-                }
-            };
         })
+        .custom_parser(move |value| {
+            // a bare number is in the unit that is displayed
+            match value.trim().parse::<f64>() {
+                Ok(number) => Some(number * pinned.factor().unwrap_or(1.0)),
+                Err(_) => parse_prefixed(value),
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNITS: [PowerUnit; 5] = [
+        PowerUnit::DbMilliwatt,
+        PowerUnit::DbWatt,
+        PowerUnit::Milliwatt,
+        PowerUnit::Watt,
+        PowerUnit::DbMicrovolt,
+    ];
+
+    fn power_in(val_dbm: f64, unit: PowerUnit) -> f64 {
+        Power { val_dbm, unit }.get_in_unit()
+    }
+
+    #[test]
+    fn power_round_trip() {
+        for val_dbm in [0.0, 30.0, -30.0, -174.0, 13.7] {
+            for unit in UNITS {
+                let mut power = Power { val_dbm, unit };
+                let val_unit = power.get_in_unit();
+                power.val_dbm = f64::NAN;
+                power.value_from_unit(val_unit);
+                assert!((power.val_dbm - val_dbm).abs() < 1e-9, "{val_dbm} dBm via {}", power.unit);
+            }
+        }
+    }
+
+    #[test]
+    fn power_anchors() {
+        assert!((power_in(30.0, PowerUnit::DbWatt) - 0.0).abs() < 1e-12);
+        assert!((power_in(30.0, PowerUnit::Watt) - 1.0).abs() < 1e-12);
+        assert!((power_in(30.0, PowerUnit::Milliwatt) - 1000.0).abs() < 1e-9);
+        assert!((power_in(0.0, PowerUnit::Milliwatt) - 1.0).abs() < 1e-12);
+        assert!((power_in(0.0, PowerUnit::DbWatt) + 30.0).abs() < 1e-12);
+        assert!((power_in(-30.0, PowerUnit::Watt) - 1e-6).abs() < 1e-18);
+        assert!((power_in(-107.0, PowerUnit::DbMicrovolt) - 0.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let mut app = LinkBudgetApp::default();
+        app.budget.frequency = 12e9;
+        app.budget.gains.insert("dish".to_string(), Gain::new(38.5));
+        let imported = LinkBudgetApp::from_json(&app.to_json().unwrap()).unwrap();
+        assert_eq!(imported.budget.frequency, 12e9);
+        assert_eq!(imported.budget.gains["dish"].value, 38.5);
+
+        assert!(LinkBudgetApp::from_json("{").is_err());
+        assert!(LinkBudgetApp::from_json(r#"{"version": 99, "state": {}}"#).is_err());
+        assert!(LinkBudgetApp::from_json(r#"{"frequency": 1e9}"#).is_err());
+    }
+
+    #[test]
+    fn length_of_older_states() {
+        let length: Length = serde_json::from_str("1500").unwrap();
+        assert_eq!(length.val_m, 1500.0);
+        assert!(length.unit == LengthUnit::Meter);
+        let length: Length = serde_json::from_str(r#"{"val_m": 1500.0, "unit": "Kilometer"}"#).unwrap();
+        assert_eq!(length.val_m, 1500.0);
+        assert!(length.unit == LengthUnit::Kilometer);
+    }
+
+    #[test]
+    fn state_of_the_first_version() {
+        let json = r#"{
+            "temperature": 290.0,
+            "frequency": 2400000000.0,
+            "bandwidth": 20000000.0,
+            "snr": 10.0,
+            "tx_power": {"val_dbm": 20.0, "unit": "DbMilliwatt"},
+            "rx_power": {"val_dbm": 0.0, "unit": "DbMilliwatt"},
+            "distance": 1500.0,
+            "d_break": 500.0,
+            "break_exponent": 4.3,
+            "losses": {"cable": 2.0, "connector": 1},
+            "loss_name": "",
+            "gains": {"antenna": 12.5},
+            "gain_name": "",
+            "calculation_target": "Distance"
+        }"#;
+        let app = LinkBudgetApp::from_state(serde_json::from_str(json).unwrap()).unwrap();
+        assert_eq!(app.budget.tx_power, 20.0);
+        assert_eq!(app.budget.distance, 1500.0);
+        assert_eq!(app.budget.losses["cable"].value, 2.0);
+        assert_eq!(app.budget.losses["connector"].value, 1.0);
+        assert_eq!(app.budget.gains["antenna"].value, 12.5);
+        assert!(app.budget.losses["cable"].condition == LossCondition::Always);
+        assert!(app.budget.target == Target::Distance);
+
+        // entries before the units had no unit
+        let loss: Loss = serde_json::from_str(r#"{"value": 6.0, "condition": "FadedOnly"}"#).unwrap();
+        assert!(loss.unit == RatioUnit::Db);
+        assert!(loss.condition == LossCondition::FadedOnly);
+        let gain: Gain = serde_json::from_str(r#"{"value": 3.0, "tx_antenna": true}"#).unwrap();
+        assert!(gain.unit == RatioUnit::Db);
+        assert!(gain.tx_antenna && gain.enabled);
+    }
+
+    #[test]
+    fn budget_of_the_defaults() {
+        let budget = LinkBudgetApp::default().budget;
+        assert_eq!(budget.total_sum(), LinkBudget::default().total_sum());
+        assert_eq!(budget.noise_stages.len(), LinkBudget::default().noise_stages.len());
+    }
+
+    #[test]
+    fn losses_keep_insertion_order() {
+        let mut app = LinkBudgetApp::default();
+        for name in ["feeder", "connector", "body", "atmosphere"] {
+            app.budget.losses.insert(name.to_string(), Loss::new(1.0));
+        }
+        let imported = LinkBudgetApp::from_json(&app.to_json().unwrap()).unwrap();
+        let names: Vec<&str> = imported.budget.losses.keys().map(String::as_str).collect();
+        assert_eq!(names, ["feeder", "connector", "body", "atmosphere"]);
+    }
+
+    #[test]
+    fn history_undo_redo() {
+        let mut history = History::default();
+        for state in ["a", "b", "b", "c"] {
+            history.record(state.to_string());
+        }
+        assert_eq!(history.undo().as_deref(), Some("b"));
+        assert_eq!(history.undo().as_deref(), Some("a"));
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.redo().as_deref(), Some("b"));
+        // a new edit drops the redo stack
+        history.record("d".to_string());
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.undo().as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn undo_restores_a_removed_antenna() {
+        let mut app = LinkBudgetApp::default();
+        app.budget.antennas.insert("dish".to_string(), ApertureAntenna::default());
+        let gains = app.budget.total_gains();
+        app.history.record(serde_json::to_string(&app.budget).unwrap());
+        app.budget.antennas.shift_remove("dish");
+        app.history.record(serde_json::to_string(&app.budget).unwrap());
+        app.undo();
+        assert!(app.budget.antennas.contains_key("dish"));
+        assert_eq!(app.budget.total_gains(), gains);
+    }
+
+    #[test]
+    fn undo_and_scenarios_keep_the_constants() {
+        let mut app = LinkBudgetApp::default();
+        app.budget.physics.constants = calc::Constants::Textbook;
+        app.history.record(serde_json::to_string(&app.budget).unwrap());
+        app.scenarios.insert("textbook".to_string(), app.budget.clone());
+        app.budget.physics.constants = calc::Constants::Exact;
+        app.history.record(serde_json::to_string(&app.budget).unwrap());
+        app.undo();
+        assert_eq!(app.budget.physics.constants, calc::Constants::Textbook);
+
+        app.budget.physics.constants = calc::Constants::Exact;
+        app.restore(app.scenarios["textbook"].clone());
+        assert_eq!(app.budget.physics.constants, calc::Constants::Textbook);
+        app.reset(ResetScope::Everything);
+        assert_eq!(app.budget.physics.constants, calc::Constants::Exact);
+    }
+
+    #[test]
+    fn rename_keeps_value_and_position() {
+        let mut losses = IndexMap::new();
+        losses.insert("feeder".to_string(), 1.0);
+        losses.insert("Cabel".to_string(), 2.5);
+        losses.insert("body".to_string(), 3.0);
+        assert!(rename_entry(&mut losses, "Cabel", "Cable"));
+        assert_eq!(losses.get_index(1), Some((&"Cable".to_string(), &2.5)));
+        assert!(!rename_entry(&mut losses, "Cable", "body"));
+        assert!(!rename_entry(&mut losses, "Cable", " "));
+        assert_eq!(losses.len(), 3);
+    }
+
+    #[test]
+    fn disabled_entries_are_skipped() {
+        let mut app = LinkBudgetApp::default();
+        app.budget.gains.insert("antenna".to_string(), Gain::new(12.0));
+        app.budget.losses.insert("fading".to_string(), Loss::new(20.0));
+        let (gains, losses) = (app.budget.total_gains(), app.budget.total_losses());
+        app.budget.gains["antenna"].enabled = false;
+        app.budget.losses["fading"].enabled = false;
+        assert_eq!(app.budget.total_gains(), gains - 12.0);
+        assert_eq!(app.budget.total_losses(), losses - 20.0);
+        assert_eq!(app.budget.losses.len(), 1);
+    }
+
+    #[test]
+    fn pattern_csv() {
+        let table = parse_pattern_csv("angle,gain\n90, 0\n0; 10\n\n180 -10\n").unwrap();
+        assert_eq!(table, vec![(0.0, 10.0), (90.0, 0.0), (180.0, -10.0)]);
+        assert!(parse_pattern_csv("angle,gain\n").is_err());
+        assert!(parse_pattern_csv("0,1\n90,x\n").is_err());
+    }
+
+    #[test]
+    fn diff_lists_entries_of_both() {
+        let mut a = LinkBudget::default();
+        a.gains.insert("antenna".to_string(), Gain::new(12.0));
+        let mut b = a.clone();
+        b.gains.clear();
+        b.losses.insert("cable".to_string(), Loss::new(2.0));
+        let rows = LinkBudgetApp::diff(&a, &b);
+        let row = |name: &str| rows.iter().find(|(n, _, _)| n == name).unwrap();
+        assert_eq!(row("gains [dB]").1 - row("gains [dB]").2, 12.0);
+        assert!(row("antenna [dB]").2.is_nan());
+        assert!(row("cable [dB]").1.is_nan());
+        assert_eq!(row("cable [dB]").2, -2.0);
+    }
+
+    #[test]
+    fn selective_reset() {
+        let mut app = LinkBudgetApp::default();
+        app.budget.frequency = 5.8e9;
+        app.budget.losses.insert("cable".to_string(), Loss::new(2.0));
+        app.reset(ResetScope::Parameters);
+        assert_eq!(app.budget.frequency, LinkBudgetApp::default().budget.frequency);
+        assert!(app.budget.losses.contains_key("cable"));
+
+        app.budget.frequency = 5.8e9;
+        app.reset(ResetScope::Tables);
+        assert_eq!(app.budget.frequency, 5.8e9);
+        assert!(app.budget.losses.is_empty());
+    }
+
+    #[test]
+    fn band_preset_limits_the_eirp() {
+        let mut app = LinkBudgetApp::default();
+        app.budget.tx_power = 20.0;
+        let mut antenna = Gain::new(6.0);
+        antenna.tx_antenna = true;
+        app.budget.gains.insert("antenna".to_string(), antenna);
+        app.apply_band_preset("WiFi 2.4 GHz (EU)");
+        assert!((app.budget.eirp() - 20.0).abs() < 1e-9);
+        assert!(app.warnings().is_empty());
+        app.budget.tx_power += 1.0;
+        assert_eq!(app.warnings().len(), 1);
+        app.budget.frequency = 5.8e9;
+        assert!(app.warnings().is_empty());
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14 22:13:20 UTC");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00:00 UTC");
+    }
+
+    #[test]
+    fn summary_lists_the_entries() {
+        let mut app = LinkBudgetApp::default();
+        app.budget.gains.insert("dish".to_string(), Gain::new(30.0));
+        app.budget.losses.insert("cable".to_string(), Loss::new(2.0));
+        app.precision = 3;
+        let summary = app.summary_text();
+        assert!(summary.contains("dish"));
+        assert!(summary.contains("-2.000 dB"));
+        assert!(summary.contains(&format!("{:+.3} dB", app.budget.link_margin())));
+    }
 }
@@ -0,0 +1,34 @@
+use std::sync::mpsc::{channel, Receiver};
+
+/// A computation running off the UI thread, `update` polls it until the result arrives
+pub struct Background<T> {
+    receiver: Receiver<T>,
+}
+
+impl<T: Send + 'static> Background<T> {
+    /// Start the computation on a worker thread and repaint once it is done. The web has no
+    /// threads, there it runs immediately.
+    pub fn spawn(ctx: &egui::Context, job: impl FnOnce() -> T + Send + 'static) -> Self {
+        let (sender, receiver) = channel();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let ctx = ctx.clone();
+            std::thread::spawn(move || {
+                // the receiver is gone when the result is no longer wanted
+                let _ = sender.send(job());
+                ctx.request_repaint();
+            });
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = ctx;
+            let _ = sender.send(job());
+        }
+        Self { receiver }
+    }
+
+    /// The result once it is ready
+    pub fn poll(&self) -> Option<T> {
+        self.receiver.try_recv().ok()
+    }
+}
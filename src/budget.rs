@@ -0,0 +1,1534 @@
+use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
+use indexmap::IndexMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{IgnoredAny, MapAccess, Visitor};
+use serde::de::value::MapAccessDeserializer;
+use crate::calc;
+use crate::calc::feeder::FeederType;
+use crate::calc::rain::Polarization;
+
+/// Steps of the distance solution, each one adjusts the path loss to the excess losses at the
+/// previous distance
+const DISTANCE_ITERATIONS: usize = 50;
+const BANDWIDTH_ITERATIONS: usize = 60;
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PropagationModel {
+    #[default]
+    Friis,
+    TwoRay,
+}
+
+impl Display for PropagationModel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PropagationModel::Friis => { write!(f, "Friis") }
+            PropagationModel::TwoRay => { write!(f, "Two ray") }
+        }
+    }
+}
+
+/// The quantity [`LinkBudget::solve`] calculates from the others
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Target {
+    #[default]
+    Snr,
+    Distance,
+    TxPower,
+    Bandwidth,
+}
+
+impl Target {
+    pub const ALL: [Target; 4] = [
+        Target::Snr,
+        Target::Distance,
+        Target::TxPower,
+        Target::Bandwidth,
+    ];
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Target::Snr => { write!(f, "SNR") }
+            Target::Distance => { write!(f, "Distance") }
+            Target::TxPower => { write!(f, "Tx power") }
+            Target::Bandwidth => { write!(f, "Bandwidth") }
+        }
+    }
+}
+
+/// Polarizations of the Tx and Rx antenna, the mismatch is added as a loss
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PolarizationMismatch {
+    #[default]
+    None,
+    LinearToLinear,
+    LinearToCircular,
+}
+
+impl Display for PolarizationMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolarizationMismatch::None => { write!(f, "Matched") }
+            PolarizationMismatch::LinearToLinear => { write!(f, "Linear to linear") }
+            PolarizationMismatch::LinearToCircular => { write!(f, "Linear to circular") }
+        }
+    }
+}
+
+/// Name of the loss entry that holds the polarization mismatch
+pub const POLARIZATION_LOSS_NAME: &str = "polarization mismatch";
+
+/// Fading statistics the fading margin is derived from
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Fading {
+    #[default]
+    None,
+    Rayleigh,
+    Rician,
+}
+
+impl Display for Fading {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fading::None => { write!(f, "No fading") }
+            Fading::Rayleigh => { write!(f, "Rayleigh") }
+            Fading::Rician => { write!(f, "Rician") }
+        }
+    }
+}
+
+/// Name of the loss entry that holds the fading margin
+pub const FADING_MARGIN_NAME: &str = "fading margin";
+
+/// Propagation condition the budget is evaluated for
+#[derive(Default, Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub enum Condition {
+    #[default]
+    Clear,
+    Faded,
+}
+
+#[derive(Default, Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub enum LossCondition {
+    #[default]
+    Always,
+    FadedOnly,
+}
+
+/// Unit a gain or loss is shown and entered in, the value itself is always stored in dB
+#[derive(Default, Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub enum RatioUnit {
+    #[default]
+    Db,
+    Linear,
+}
+
+impl Display for RatioUnit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RatioUnit::Db => { write!(f, "dB") }
+            RatioUnit::Linear => { write!(f, "lin") }
+        }
+    }
+}
+
+impl RatioUnit {
+    pub fn db_to_unit(&self, value_db: f64) -> f64 {
+        match self {
+            RatioUnit::Db => { value_db }
+            RatioUnit::Linear => { f64::powf(10.0, value_db / 10.0) }
+        }
+    }
+
+    pub fn unit_to_db(&self, value: f64) -> f64 {
+        match self {
+            RatioUnit::Db => { value }
+            RatioUnit::Linear => { 10.0 * f64::log10(value) }
+        }
+    }
+}
+
+/// How a gain or loss entered at a reference frequency changes with the frequency
+#[derive(Default, Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub enum FrequencyScaling {
+    #[default]
+    Flat,
+    /// dB value proportional to sqrt(f), like the skin effect loss of a cable
+    SqrtF,
+    /// linear value proportional to f^2, like the gain of a fixed aperture antenna
+    FSquared,
+    /// dB value changing by the slope per octave of frequency
+    PerOctave,
+    /// dB value changing by the slope per GHz of frequency
+    PerGhz,
+}
+
+impl Display for FrequencyScaling {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrequencyScaling::Flat => { write!(f, "flat") }
+            FrequencyScaling::SqrtF => { write!(f, "√f") }
+            FrequencyScaling::FSquared => { write!(f, "f²") }
+            FrequencyScaling::PerOctave => { write!(f, "dB/oct") }
+            FrequencyScaling::PerGhz => { write!(f, "dB/GHz") }
+        }
+    }
+}
+
+impl FrequencyScaling {
+    /// Value at the frequency, the slope in dB per octave or per GHz is used by the slope
+    /// scalings only
+    pub fn apply(&self, value_db: f64, reference_frequency: f64, slope: f64, frequency: f64) -> f64 {
+        match self {
+            FrequencyScaling::Flat => { value_db }
+            FrequencyScaling::SqrtF => { value_db * f64::sqrt(frequency / reference_frequency) }
+            FrequencyScaling::FSquared => { value_db + 20.0 * f64::log10(frequency / reference_frequency) }
+            FrequencyScaling::PerOctave => { value_db + slope * f64::log2(frequency / reference_frequency) }
+            FrequencyScaling::PerGhz => { value_db + slope * (frequency - reference_frequency) / 1e9 }
+        }
+    }
+
+    pub fn has_slope(&self) -> bool {
+        matches!(self, FrequencyScaling::PerOctave | FrequencyScaling::PerGhz)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(remote = "Gain")]
+pub struct Gain {
+    pub value: f64, // dB at reference_frequency
+    #[serde(default)]
+    pub unit: RatioUnit,
+    #[serde(default)]
+    pub scaling: FrequencyScaling,
+    #[serde(default)]
+    pub reference_frequency: f64, // Hertz
+    #[serde(default)]
+    pub slope: f64, // dB per octave or per GHz
+    #[serde(default)]
+    pub tx_antenna: bool, // counts towards the EIRP
+    #[serde(default)]
+    pub category: String,
+    #[serde(default)]
+    pub tolerance: f64, // dB, standard deviation
+    #[serde(default = "default_enabled")]
+    pub enabled: bool, // counts towards the budget
+    #[serde(default)]
+    pub pattern: Option<AntennaPattern>, // replaces value and scaling when set
+}
+
+/// Gain versus angle of an antenna, evaluated at the pointing angle
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AntennaPattern {
+    pub table: Vec<(f64, f64)>, // degree, dBi, sorted by angle
+    pub angle: f64,             // degree off boresight
+}
+
+/// Entries of older files are enabled
+fn default_enabled() -> bool {
+    true
+}
+
+impl Gain {
+    pub fn new(value: f64) -> Self {
+        Self {
+            value,
+            unit: RatioUnit::Db,
+            scaling: FrequencyScaling::Flat,
+            reference_frequency: 0.0,
+            slope: 0.0,
+            tx_antenna: false,
+            category: String::new(),
+            tolerance: 0.0,
+            enabled: true,
+            pattern: None,
+        }
+    }
+
+    pub fn pattern(table: Vec<(f64, f64)>) -> Self {
+        let mut gain = Self::new(calc::antenna::interpolate_gain(&table, 0.0));
+        gain.pattern = Some(AntennaPattern { table, angle: 0.0 });
+        gain
+    }
+
+    pub fn value_at(&self, frequency: f64) -> f64 {
+        match &self.pattern {
+            Some(pattern) => { calc::antenna::interpolate_gain(&pattern.table, pattern.angle) }
+            None => { self.scaling.apply(self.value, self.reference_frequency, self.slope, frequency) }
+        }
+    }
+}
+
+impl Serialize for Gain {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Gain::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Gain {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(EntryVisitor(PhantomData))
+    }
+}
+
+impl Entry for Gain {
+    fn from_db(value: f64) -> Self {
+        Gain::new(value)
+    }
+
+    fn from_fields<'de, A: MapAccess<'de>>(map: A) -> Result<Self, A::Error> {
+        Gain::deserialize(MapAccessDeserializer::new(map))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(remote = "Loss")]
+pub struct Loss {
+    pub value: f64, // dB at reference_frequency
+    #[serde(default)]
+    pub unit: RatioUnit,
+    #[serde(default)]
+    pub condition: LossCondition,
+    #[serde(default)]
+    pub scaling: FrequencyScaling,
+    #[serde(default)]
+    pub reference_frequency: f64, // Hertz
+    #[serde(default)]
+    pub slope: f64, // dB per octave or per GHz
+    #[serde(default)]
+    pub tx_feed: bool, // counts towards the EIRP
+    #[serde(default)]
+    pub category: String,
+    #[serde(default)]
+    pub cable_run: Option<CableRun>, // replaces value and scaling when set
+    #[serde(default)]
+    pub tolerance: f64, // dB, standard deviation
+    #[serde(default = "default_enabled")]
+    pub enabled: bool, // counts towards the budget
+}
+
+impl Loss {
+    pub fn new(value: f64) -> Self {
+        Self {
+            value,
+            unit: RatioUnit::Db,
+            condition: LossCondition::Always,
+            scaling: FrequencyScaling::Flat,
+            reference_frequency: 0.0,
+            slope: 0.0,
+            tx_feed: false,
+            category: String::new(),
+            cable_run: None,
+            tolerance: 0.0,
+            enabled: true,
+        }
+    }
+
+    pub fn cable(cable: CableType, length: f64, frequency: f64) -> Self {
+        let mut loss = Self::new(cable.loss(length, frequency));
+        loss.cable_run = Some(CableRun { cable, length });
+        loss
+    }
+
+    pub fn value_at(&self, frequency: f64) -> f64 {
+        match &self.cable_run {
+            Some(run) => { run.cable.loss(run.length, frequency) }
+            None => { self.scaling.apply(self.value, self.reference_frequency, self.slope, frequency) }
+        }
+    }
+
+    /// Enabled and counted in the condition
+    pub fn applies(&self, condition: Condition) -> bool {
+        self.enabled && (self.condition == LossCondition::Always || condition == Condition::Faded)
+    }
+}
+
+impl Serialize for Loss {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Loss::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Loss {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(EntryVisitor(PhantomData))
+    }
+}
+
+impl Entry for Loss {
+    fn from_db(value: f64) -> Self {
+        Loss::new(value)
+    }
+
+    fn from_fields<'de, A: MapAccess<'de>>(map: A) -> Result<Self, A::Error> {
+        Loss::deserialize(MapAccessDeserializer::new(map))
+    }
+}
+
+/// A gain or loss of the tables, states of older versions stored a bare number in dB
+trait Entry: Sized {
+    fn from_db(value: f64) -> Self;
+    fn from_fields<'de, A: MapAccess<'de>>(map: A) -> Result<Self, A::Error>;
+}
+
+/// Reads an entry from its fields or from a bare number. An untagged enum would not do, it reads
+/// the value as self describing and the RON of the app storage does not describe the enum fields.
+struct EntryVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Entry> Visitor<'de> for EntryVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "a value in dB or an entry")
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<T, E> {
+        Ok(T::from_db(value))
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<T, E> {
+        Ok(T::from_db(value as f64))
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<T, E> {
+        Ok(T::from_db(value as f64))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<T, A::Error> {
+        T::from_fields(map)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TermKind {
+    TxPower,
+    Gain,
+    Antenna,
+    PathLoss,
+    Atmosphere,
+    Rain,
+    Diffraction,
+    Loss,
+    NoiseFloor,
+    RequiredSnr,
+    ImplementationMargin,
+}
+
+impl TermKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            TermKind::TxPower => { "transmit power" }
+            TermKind::Gain => { "gain" }
+            TermKind::Antenna => { "aperture antenna gain at the current frequency" }
+            TermKind::PathLoss => { "path loss of the propagation model" }
+            TermKind::Atmosphere => { "absorption by atmospheric gases" }
+            TermKind::Rain => { "rain attenuation along the path" }
+            TermKind::Diffraction => { "knife edge diffraction at the obstacle" }
+            TermKind::Loss => { "loss" }
+            TermKind::NoiseFloor => { "system noise floor k T B plus noise figure" }
+            TermKind::RequiredSnr => { "SNR required by the receiver" }
+            TermKind::ImplementationMargin => { "safety margin kept on top of the budget" }
+        }
+    }
+}
+
+/// One signed contribution to the link budget in dB
+pub struct BudgetTerm {
+    pub name: String,
+    pub value: f64,
+    pub kind: TermKind,
+}
+
+impl BudgetTerm {
+    pub fn new(name: &str, value: f64, kind: TermKind) -> Self {
+        Self {
+            name: name.to_string(),
+            value,
+            kind,
+        }
+    }
+}
+
+/// Antenna with a fixed physical aperture, its gain follows the frequency
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApertureAntenna {
+    pub diameter: f64,   // meter
+    pub efficiency: f64, // 0..1
+}
+
+impl Default for ApertureAntenna {
+    fn default() -> Self {
+        Self {
+            diameter: 1.0,
+            efficiency: calc::antenna::DEFAULT_EFFICIENCY,
+        }
+    }
+}
+
+impl ApertureAntenna {
+    pub fn gain(&self, frequency: f64, physics: calc::Physics) -> f64 {
+        calc::antenna::parabolic_gain(self.diameter, frequency, self.efficiency, physics)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CableType {
+    pub name: String,
+    pub loss_per_100m: f64,       // dB at reference_frequency
+    pub reference_frequency: f64, // Hertz
+    pub frequency_exponent: f64,
+    #[serde(default)]
+    pub feeder: Option<FeederType>, // catalog loss curve, replaces the fit above when set
+}
+
+impl CableType {
+    pub fn new(name: &str, loss_per_100m: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            loss_per_100m,
+            reference_frequency: 1e9,
+            frequency_exponent: 0.5,
+            feeder: None,
+        }
+    }
+
+    pub fn feeder(feeder: FeederType) -> Self {
+        Self {
+            feeder: Some(feeder),
+            ..Self::new(&feeder.to_string(), 0.0)
+        }
+    }
+
+    pub fn loss(&self, length: f64, frequency: f64) -> f64 {
+        if let Some(feeder) = self.feeder {
+            return calc::feeder::loss(feeder, length, frequency);
+        }
+        let loss_per_meter = calc::cable_loss_per_meter(
+            self.loss_per_100m,
+            self.reference_frequency,
+            self.frequency_exponent,
+            frequency,
+        );
+        calc::cable_loss(length, loss_per_meter)
+    }
+}
+
+/// A length of cable, the loss is evaluated at the frequency in use
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CableRun {
+    pub cable: CableType,
+    pub length: f64, // meter
+}
+
+/// Stage of a receive chain for the noise figure cascade
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NoiseStage {
+    pub name: String,
+    pub gain: f64,         // dB, the negative loss of a passive stage
+    pub noise_figure: f64, // dB, the loss of a passive stage
+}
+
+impl NoiseStage {
+    pub fn new(name: &str, gain: f64, noise_figure: f64) -> Self {
+        Self { name: name.to_string(), gain, noise_figure }
+    }
+}
+
+/// Further hop of a relayed link, from an amplify and forward relay to the next relay or the Rx
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hop {
+    pub distance: f64,     // meter
+    pub gains: f64,        // dB, antennas at both ends
+    pub losses: f64,       // dB
+    pub relay_gain: f64,   // dB, of the relay at the start
+    pub noise_figure: f64, // dB, of the receiver at the end
+}
+
+impl Default for Hop {
+    fn default() -> Self {
+        Self {
+            distance: 2000.0,
+            gains: 0.0,
+            losses: 0.0,
+            relay_gain: 60.0,
+            noise_figure: 0.0,
+        }
+    }
+}
+
+/// Loss entries of the propagation, the computed polarization and fading entries and the ones
+/// that only apply when faded
+fn is_environmental(name: &str, loss: &Loss) -> bool {
+    name == POLARIZATION_LOSS_NAME || name == FADING_MARGIN_NAME || loss.condition == LossCondition::FadedOnly
+}
+
+
+/// A link budget with everything the calculation needs. The user interface and the command line
+/// both solve it, missing fields of a serialized budget take the default values.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkBudget {
+    /// The quantity [`LinkBudget::solve`] calculates. It is part of the budget and not an
+    /// argument of the solution since the derived values depend on it: a solved distance is not
+    /// replaced by the slant range and outside the Snr target the SNR is set to the one the data
+    /// rate or the sensitivity require. It is saved with the other inputs as well.
+    #[serde(alias = "calculation_target")]
+    pub target: Target,
+    /// The constants and the medium of the noise and wavelength calculations
+    pub physics: calc::Physics,
+    /// Hertz
+    pub frequency: f64,
+    /// Hertz
+    pub bandwidth: f64,
+    /// Noise temperature in Kelvin
+    pub temperature: f64,
+    /// dB
+    pub noise_figure: f64,
+    /// The noise figure is the cascade of the noise stages
+    pub noise_cascade: bool,
+    /// In signal order
+    pub noise_stages: Vec<NoiseStage>,
+    /// Antenna plus receiver temperature instead of the temperature and the noise figure
+    pub separate_temperatures: bool,
+    /// Kelvin
+    pub antenna_temperature: f64,
+    /// Kelvin
+    pub receiver_temperature: f64,
+    /// Refer the noise of antenna, feedline and LNA to the antenna terminal for the G/T
+    pub noise_chain: bool,
+    /// dB
+    pub feedline_loss: f64,
+    /// Kelvin
+    pub feedline_temperature: f64,
+    /// dB
+    pub lna_noise_figure: f64,
+    /// dBi, of the G/T
+    pub rx_antenna_gain: f64,
+    /// dBm
+    #[serde(deserialize_with = "si_value")]
+    pub tx_power: f64,
+    /// dB, standard deviation of the Tx power
+    pub tx_power_tolerance: f64,
+    /// meter
+    #[serde(deserialize_with = "si_value")]
+    pub distance: f64,
+    /// meter, beyond it the path loss grows with the break exponent
+    #[serde(deserialize_with = "si_value")]
+    pub d_break: f64,
+    pub break_exponent: f64,
+    /// dB, free space path loss at 1 m and 1 GHz
+    pub reference_loss: f64,
+    /// The break distance is the far field distance of the break aperture
+    pub break_at_far_field: bool,
+    /// meter
+    pub break_aperture: f64,
+    pub propagation_model: PropagationModel,
+    /// meter, of the two ray model
+    pub tx_height: f64,
+    /// meter, of the two ray model
+    pub rx_height: f64,
+    /// Absorption by atmospheric gases
+    pub atmosphere: bool,
+    /// Kelvin
+    pub air_temperature: f64,
+    /// hPa
+    pub pressure: f64,
+    /// g/m^3
+    pub water_vapor_density: f64,
+    /// mm/h
+    pub rain_rate: f64,
+    pub polarization: Polarization,
+    /// Knife edge obstacle between Tx and Rx
+    pub obstacle: bool,
+    /// meter above the line of sight
+    pub obstacle_height: f64,
+    /// meter from the Tx
+    pub obstacle_distance: f64,
+    /// The distance is the slant range to a satellite
+    pub slant_path: bool,
+    /// km
+    pub orbit_altitude: f64,
+    /// degree
+    pub elevation: f64,
+    /// dB, the target of the other solutions
+    pub snr: f64,
+    /// dB, the SNR the receiver needs
+    pub required_snr: f64,
+    /// The required SNR follows from the data rate
+    pub use_data_rate: bool,
+    /// bit/s
+    pub data_rate: f64,
+    /// The data rate needs the Eb/N0 instead of the Shannon limit
+    pub use_ebn0: bool,
+    /// dB
+    pub required_ebn0: f64,
+    /// The required SNR follows from the receiver sensitivity
+    pub use_sensitivity: bool,
+    /// dBm
+    pub rx_sensitivity: f64,
+    /// dB, subtracted from the SNR
+    pub implementation_margin: f64,
+    /// Named gains, in the order they were added
+    pub gains: IndexMap<String, Gain>,
+    /// Named losses, in the order they were added
+    pub losses: IndexMap<String, Loss>,
+    /// Named aperture antennas, their gain follows the frequency
+    pub antennas: IndexMap<String, ApertureAntenna>,
+    pub condition: Condition,
+    pub polarization_mismatch: PolarizationMismatch,
+    /// degree
+    pub polarization_tilt: f64,
+    pub fading: Fading,
+    /// percent
+    pub availability: f64,
+    /// linear
+    pub k_factor: f64,
+    /// After the first one to the first relay, which is the budget itself
+    pub hops: Vec<Hop>,
+}
+
+impl Default for LinkBudget {
+    fn default() -> Self {
+        Self {
+            target: Target::default(),
+            physics: calc::Physics::default(),
+            frequency: 2.4e9,
+            bandwidth: 20e6,
+            temperature: 290.0,
+            noise_figure: 0.0,
+            noise_cascade: false,
+            noise_stages: vec![
+                NoiseStage::new("LNA", 20.0, 1.0),
+                NoiseStage::new("Cable", -3.0, 3.0),
+                NoiseStage::new("Mixer", -7.0, 10.0),
+            ],
+            separate_temperatures: false,
+            antenna_temperature: 50.0,
+            receiver_temperature: 75.0,
+            noise_chain: false,
+            feedline_loss: 0.5,
+            feedline_temperature: 290.0,
+            lna_noise_figure: 1.0,
+            rx_antenna_gain: 30.0,
+            tx_power: 0.0,
+            tx_power_tolerance: 0.0,
+            distance: 2000.0,
+            d_break: 500.0,
+            break_exponent: 4.3,
+            reference_loss: calc::friis::DEFAULT_REFERENCE_LOSS,
+            break_at_far_field: false,
+            break_aperture: 1.0,
+            propagation_model: PropagationModel::default(),
+            tx_height: 10.0,
+            rx_height: 2.0,
+            atmosphere: false,
+            air_temperature: 288.15,
+            pressure: 1013.25,
+            water_vapor_density: 7.5,
+            rain_rate: 0.0,
+            polarization: Polarization::default(),
+            obstacle: false,
+            obstacle_height: 0.0,
+            obstacle_distance: 1000.0,
+            slant_path: false,
+            orbit_altitude: 550.0,
+            elevation: 30.0,
+            snr: 10.0,
+            required_snr: 10.0,
+            use_data_rate: false,
+            data_rate: 10e6,
+            use_ebn0: false,
+            required_ebn0: 10.5,
+            use_sensitivity: false,
+            rx_sensitivity: -90.0,
+            implementation_margin: 0.0,
+            gains: IndexMap::new(),
+            losses: IndexMap::new(),
+            antennas: IndexMap::new(),
+            condition: Condition::default(),
+            polarization_mismatch: PolarizationMismatch::default(),
+            polarization_tilt: 0.0,
+            fading: Fading::default(),
+            availability: 99.9,
+            k_factor: 5.0,
+            hops: Vec::new(),
+        }
+    }
+}
+
+/// Reads a value in SI units. The app stored the distances and the Tx power together with the
+/// unit they are shown in before it kept the budget in one field, the value is taken from those.
+fn si_value<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    deserializer.deserialize_any(SiValueVisitor)
+}
+
+struct SiValueVisitor;
+
+impl<'de> Visitor<'de> for SiValueVisitor {
+    type Value = f64;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "a value in SI units or a value with its unit")
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<f64, E> {
+        Ok(value)
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<f64, E> {
+        Ok(value as f64)
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<f64, E> {
+        Ok(value as f64)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<f64, A::Error> {
+        let mut value = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if key.starts_with("val_") {
+                value = Some(map.next_value::<f64>()?);
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        value.ok_or_else(|| serde::de::Error::custom("the value is missing"))
+    }
+}
+
+impl LinkBudget {
+    pub fn total_losses(&self) -> f64 {
+        self.losses_in(self.condition)
+    }
+
+    /// Losses in dB of the entries that apply in the condition
+    pub fn losses_in(&self, condition: Condition) -> f64 {
+        self.losses
+            .iter()
+            .filter(|(_, l)| l.applies(condition))
+            .map(|(_, l)| l.value_at(self.frequency))
+            .sum()
+    }
+
+    /// Propagation impairments in dB in the condition: gases, rain and diffraction, the
+    /// polarization mismatch, the fading margin and the losses that only apply when faded
+    pub fn environmental_losses_in(&self, condition: Condition) -> f64 {
+        let entries: f64 = self
+            .losses
+            .iter()
+            .filter(|(name, l)| l.applies(condition) && is_environmental(name, l))
+            .map(|(_, l)| l.value_at(self.frequency))
+            .sum();
+        self.excess_loss() + entries
+    }
+
+    /// Losses in dB of the hardware in the condition, the losses that are not environmental
+    pub fn equipment_losses_in(&self, condition: Condition) -> f64 {
+        self.losses
+            .iter()
+            .filter(|(name, l)| l.applies(condition) && !is_environmental(name, l))
+            .map(|(_, l)| l.value_at(self.frequency))
+            .sum()
+    }
+
+    pub fn total_gains(&self) -> f64 {
+        let antennas: f64 = self.antennas.iter().map(|(_, a)| a.gain(self.frequency, self.physics)).sum();
+        self.gains.values().filter(|g| g.enabled).map(|g| g.value_at(self.frequency)).sum::<f64>() + antennas
+    }
+
+    /// Thermal noise floor plus the receiver noise figure in dBm, or the noise floor of the
+    /// antenna and receiver temperatures
+    pub fn noise_floor(&self) -> f64 {
+        if self.separate_temperatures {
+            let t_sys = calc::system_noise_temperature(self.antenna_temperature, self.receiver_temperature);
+            return calc::watt_to_dbm(calc::thermal_noise_power(t_sys, self.bandwidth, self.physics));
+        }
+        calc::noise_floor_with_nf(self.temperature, self.bandwidth, self.noise_figure, self.physics)
+    }
+
+    /// Surplus of the received power over the noise floor, the implementation margin and the SNR
+    /// in dB, zero when the budget is balanced
+    pub fn total_sum(&self) -> f64 {
+        let thermal = self.noise_floor();
+        let losses = self.total_losses();
+        let gains = self.total_gains();
+        let path = self.path_loss();
+
+        let excess = self.excess_loss();
+
+        let negative =
+            thermal
+                + losses
+                + path
+                + excess
+                + self.implementation_margin
+                + self.snr;
+        let positive = self.tx_power + gains;
+
+        return positive - negative;
+    }
+
+    /// Tx power plus the gains tagged as Tx antenna minus the losses tagged as Tx feed, in dBm
+    pub fn eirp(&self) -> f64 {
+        let antenna_gain: f64 = self
+            .gains
+            .values()
+            .filter(|g| g.tx_antenna && g.enabled)
+            .map(|g| g.value_at(self.frequency))
+            .sum();
+        let feed_loss: f64 = self
+            .losses
+            .values()
+            .filter(|l| l.tx_feed && l.applies(self.condition))
+            .map(|l| l.value_at(self.frequency))
+            .sum();
+        calc::eirp(self.tx_power, antenna_gain, feed_loss)
+    }
+
+    /// Path loss of the selected propagation model
+    pub fn path_loss_at(&self, distance: f64, d_break: f64, frequency: f64) -> f64 {
+        match self.propagation_model {
+            PropagationModel::Friis => {
+                calc::friis::path_loss(distance, d_break, frequency, self.break_exponent, self.reference_loss)
+            }
+            PropagationModel::TwoRay => {
+                calc::two_ray::path_loss(distance, self.tx_height, self.rx_height, frequency, self.physics)
+            }
+        }
+    }
+
+    pub fn path_loss(&self) -> f64 {
+        self.path_loss_at(self.distance, self.d_break, self.frequency)
+    }
+
+    /// Distance at which the selected propagation model has the given path loss
+    fn distance_for_path_loss(&self, path_loss: f64) -> f64 {
+        match self.propagation_model {
+            PropagationModel::Friis => {
+                let (d_break, exponent) = (self.d_break, self.break_exponent);
+                calc::friis::distance(path_loss, d_break, self.frequency, exponent, self.reference_loss)
+            }
+            PropagationModel::TwoRay => {
+                calc::two_ray::distance(path_loss, self.tx_height, self.rx_height, self.frequency, self.physics)
+            }
+        }
+    }
+
+    /// Gas absorption in dB, zero when the atmosphere is not modelled. A slant path crosses the
+    /// atmosphere once, the zenith loss grows with the airmass at the elevation.
+    pub fn atmospheric_loss_at(&self, frequency: f64, distance: f64) -> f64 {
+        if !self.atmosphere {
+            return 0.0;
+        }
+        let (temperature, pressure, water_vapor_density) = (self.air_temperature, self.pressure, self.water_vapor_density);
+        if let Some(elevation) = self.slant_elevation() {
+            let zenith = calc::atmospheric::zenith_gas_loss(frequency, temperature, pressure, water_vapor_density);
+            return zenith * calc::geometry::airmass(elevation);
+        }
+        calc::atmospheric::gas_loss(frequency, distance, temperature, pressure, water_vapor_density)
+    }
+
+    pub fn atmospheric_loss(&self) -> f64 {
+        self.atmospheric_loss_at(self.frequency, self.distance)
+    }
+
+    /// Rain attenuation in dB, zero without rain. On a slant path the zenith attenuation grows
+    /// with the airmass at the elevation.
+    pub fn rain_loss_at(&self, frequency: f64, distance: f64) -> f64 {
+        if let Some(elevation) = self.slant_elevation() {
+            let zenith = calc::rain::zenith_attenuation(frequency, self.rain_rate, self.polarization);
+            return zenith * calc::geometry::airmass(elevation);
+        }
+        calc::rain::path_attenuation(frequency, distance, self.rain_rate, self.polarization)
+    }
+
+    pub fn rain_loss(&self) -> f64 {
+        self.rain_loss_at(self.frequency, self.distance)
+    }
+
+    /// Knife edge diffraction loss of the obstacle in dB, zero without an obstacle or when the
+    /// obstacle is not between Tx and Rx
+    pub fn diffraction_loss_at(&self, frequency: f64, distance: f64) -> f64 {
+        if !self.obstacle || self.obstacle_distance <= 0.0 || self.obstacle_distance >= distance {
+            return 0.0;
+        }
+        let d2 = distance - self.obstacle_distance;
+        let v = calc::diffraction::parameter(self.obstacle_height, self.obstacle_distance, d2, frequency, self.physics);
+        calc::diffraction::knife_edge(v)
+    }
+
+    pub fn diffraction_loss(&self) -> f64 {
+        self.diffraction_loss_at(self.frequency, self.distance)
+    }
+
+    /// Distance dependent losses on top of the propagation model, gases, rain and diffraction
+    pub fn excess_loss_at(&self, frequency: f64, distance: f64) -> f64 {
+        self.atmospheric_loss_at(frequency, distance)
+            + self.rain_loss_at(frequency, distance)
+            + self.diffraction_loss_at(frequency, distance)
+    }
+
+    pub fn excess_loss(&self) -> f64 {
+        self.excess_loss_at(self.frequency, self.distance)
+    }
+
+    /// SNR in dB at the current distance, the Rx power minus the noise floor and the
+    /// implementation margin
+    pub fn solve_snr(&self) -> f64 {
+        let rx_power = self.tx_power + self.total_gains() - self.total_losses() - self.path_loss()
+            - self.excess_loss();
+        rx_power - self.noise_floor() - self.implementation_margin
+    }
+
+    /// Tx power in dBm that gives exactly the SNR at the current distance
+    pub fn solve_tx_power(&self) -> f64 {
+        self.snr + self.noise_floor() + self.total_losses() + self.path_loss() + self.excess_loss()
+            + self.implementation_margin
+            - self.total_gains()
+    }
+
+    /// Distance in meter that gives exactly the SNR. The gas, rain and diffraction losses change
+    /// with the distance, the path loss is adjusted until the distance settles.
+    pub fn solve_distance(&self) -> f64 {
+        let mut budget = self.clone();
+        for _ in 0..DISTANCE_ITERATIONS {
+            let distance = budget.distance_for_path_loss(budget.path_loss() + budget.total_sum());
+            if !distance.is_finite() {
+                return distance;
+            }
+            let settled = (distance - budget.distance).abs() <= 1e-9 * distance;
+            budget.distance = distance;
+            if settled {
+                break;
+            }
+        }
+        budget.distance
+    }
+
+    /// SNR in dB at the end of each hop of a relayed link before the implementation margin, the
+    /// first hop is the budget itself. A relay amplifies the power received over the previous hop
+    /// by its relay gain.
+    pub fn hop_snrs(&self) -> Vec<f64> {
+        let mut rx_power = self.solve_snr() + self.implementation_margin + self.noise_floor();
+        let mut snrs = vec![rx_power - self.noise_floor()];
+        let d_break = self.d_break_at(self.frequency);
+        for hop in &self.hops {
+            let path_loss = self.path_loss_at(hop.distance, d_break, self.frequency);
+            rx_power += hop.relay_gain + hop.gains - hop.losses - path_loss;
+            snrs.push(rx_power - self.relay_noise_floor(hop.noise_figure));
+        }
+        snrs
+    }
+
+    /// Noise floor in dBm of a relay receiver with the noise figure, in the noise setting of the
+    /// budget. With separate temperatures the receiver temperature follows from the noise figure.
+    fn relay_noise_floor(&self, noise_figure: f64) -> f64 {
+        if self.separate_temperatures {
+            let t_receiver = calc::noise_temperature(noise_figure);
+            let t_sys = calc::system_noise_temperature(self.antenna_temperature, t_receiver);
+            return calc::watt_to_dbm(calc::thermal_noise_power(t_sys, self.bandwidth, self.physics));
+        }
+        calc::noise_floor_with_nf(self.temperature, self.bandwidth, noise_figure, self.physics)
+    }
+
+    /// End to end SNR in dB over all hops, the implementation margin is subtracted once
+    pub fn end_to_end_snr(&self) -> f64 {
+        calc::relay_snr(&self.hop_snrs()) - self.implementation_margin
+    }
+
+    /// Bandwidth in Hertz that balances the budget. The noise power is proportional to the
+    /// bandwidth, so the surplus of the budget scales the bandwidth directly. When the required
+    /// SNR follows from the data rate at the Shannon limit it falls with the bandwidth as well and
+    /// the solution is the smallest bandwidth that carries the data rate. With the Eb/N0 or the
+    /// receiver sensitivity the bandwidth cancels out of the budget, there is no solution.
+    pub fn solve_bandwidth(&self) -> f64 {
+        if self.use_sensitivity || (self.use_data_rate && self.use_ebn0) {
+            return f64::NAN;
+        }
+        if !self.use_data_rate {
+            return self.bandwidth * f64::powf(10.0, self.total_sum() / 10.0);
+        }
+        let surplus = |bandwidth: f64| {
+            let mut budget = self.clone();
+            budget.target = Target::Bandwidth;
+            budget.bandwidth = bandwidth;
+            if !budget.data_rate_achievable() {
+                return f64::NEG_INFINITY;
+            }
+            budget.apply_data_rate();
+            budget.total_sum()
+        };
+        // the surplus rises with the bandwidth towards its Eb/N0 above the Shannon limit, bisect
+        // over the bandwidth in log steps
+        let (mut low, mut high) = (self.data_rate / 1e3, self.data_rate * 1e6);
+        if !(surplus(low) < 0.0 && surplus(high) > 0.0) {
+            return f64::NAN;
+        }
+        for _ in 0..BANDWIDTH_ITERATIONS {
+            let middle = f64::sqrt(low * high);
+            if surplus(middle) < 0.0 {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        high
+    }
+
+    /// Signed contributions to the received power in dB, the noise floor is not included
+    pub fn breakdown(&self) -> Vec<BudgetTerm> {
+        self.breakdown_at(self.frequency, self.distance)
+    }
+
+    /// The breakdown as if the link operated at another frequency and distance
+    pub fn breakdown_at(&self, frequency: f64, distance: f64) -> Vec<BudgetTerm> {
+        let d_break = self.d_break_at(frequency);
+        let mut terms = vec![BudgetTerm::new("Tx power", self.tx_power, TermKind::TxPower)];
+        terms.extend(
+            self.gains
+                .iter()
+                .filter(|(_, g)| g.enabled)
+                .map(|(name, g)| BudgetTerm::new(name, g.value_at(frequency), TermKind::Gain)),
+        );
+        terms.extend(
+            self.antennas
+                .iter()
+                .map(|(name, a)| BudgetTerm::new(name, a.gain(frequency, self.physics), TermKind::Antenna)),
+        );
+        let path = self.path_loss_at(distance, d_break, frequency);
+        terms.push(BudgetTerm::new("path loss", -path, TermKind::PathLoss));
+        if self.atmosphere {
+            let gas = self.atmospheric_loss_at(frequency, distance);
+            terms.push(BudgetTerm::new("atmospheric gases", -gas, TermKind::Atmosphere));
+        }
+        if self.rain_rate > 0.0 {
+            let rain = self.rain_loss_at(frequency, distance);
+            terms.push(BudgetTerm::new("rain", -rain, TermKind::Rain));
+        }
+        if self.obstacle {
+            let diffraction = self.diffraction_loss_at(frequency, distance);
+            terms.push(BudgetTerm::new("diffraction", -diffraction, TermKind::Diffraction));
+        }
+        terms.extend(
+            self.losses
+                .iter()
+                .filter(|(_, l)| l.applies(self.condition))
+                .map(|(name, l)| BudgetTerm::new(name, -l.value_at(frequency), TermKind::Loss)),
+        );
+        terms
+    }
+
+    /// Every term of the link margin, the breakdown plus the noise floor and the required SNR
+    pub fn margin_terms(&self) -> Vec<BudgetTerm> {
+        let mut terms = self.breakdown();
+        let thermal = self.noise_floor();
+        terms.push(BudgetTerm::new("noise floor", -thermal, TermKind::NoiseFloor));
+        terms.push(BudgetTerm::new("required SNR", -self.required_snr, TermKind::RequiredSnr));
+        if self.implementation_margin != 0.0 {
+            let margin = -self.implementation_margin;
+            terms.push(BudgetTerm::new("implementation margin", margin, TermKind::ImplementationMargin));
+        }
+        terms
+    }
+
+    /// Signed contributions from the Tx power down to the link margin, in budget order: the Tx
+    /// power, gains, path loss, losses, the noise floor and the required SNR. Their sum is the
+    /// link margin.
+    pub fn waterfall_terms(&self) -> Vec<(String, f64)> {
+        let mut terms = self.margin_terms();
+        terms[1..].sort_by_key(|term| term.kind as u8);
+        terms.into_iter().map(|term| (term.name, term.value)).collect()
+    }
+
+    /// Break distance in meter at the frequency, the far field distance of the break aperture
+    /// follows the frequency
+    pub fn d_break_at(&self, frequency: f64) -> f64 {
+        if self.break_at_far_field {
+            calc::fraunhofer_distance(self.break_aperture, frequency, self.physics)
+        } else {
+            self.d_break
+        }
+    }
+
+    /// Path loss at the current distance for log spaced frequencies from f_start to f_stop
+    pub fn sweep_path_loss(&self, f_start: f64, f_stop: f64, steps: usize) -> Vec<(f64, f64)> {
+        let steps = steps.max(2);
+        let ratio = f64::powf(f_stop / f_start, 1.0 / (steps - 1) as f64);
+        (0..steps)
+            .map(|i| {
+                let frequency = f_start * f64::powi(ratio, i as i32);
+                let path_loss = self.path_loss_at(self.distance, self.d_break_at(frequency), frequency);
+                (frequency, path_loss)
+            })
+            .collect()
+    }
+
+    /// Received power (dBm) and SNR (dB) for log spaced distances from d_start to d_stop
+    pub fn sweep_over_distance(&self, d_start: f64, d_stop: f64, steps: usize) -> Vec<(f64, f64, f64)> {
+        let steps = steps.max(2);
+        let ratio = f64::powf(d_stop / d_start, 1.0 / (steps - 1) as f64);
+        let noise_floor = self.noise_floor();
+        (0..steps)
+            .map(|i| {
+                let distance = d_start * f64::powi(ratio, i as i32);
+                let rx_power: f64 = self.breakdown_at(self.frequency, distance).iter().map(|term| term.value).sum();
+                (distance, rx_power, rx_power - noise_floor - self.implementation_margin)
+            })
+            .collect()
+    }
+
+    /// Link margin as if the link operated at another frequency and distance
+    pub fn margin_at(&self, frequency: f64, distance: f64) -> f64 {
+        let received: f64 = self.breakdown_at(frequency, distance).iter().map(|term| term.value).sum();
+        let thermal = self.noise_floor();
+        received - thermal - self.required_snr - self.implementation_margin
+    }
+
+    /// Derivative of the link margin with respect to frequency in dB/Hz, at the operating point.
+    /// The budget is solved for the SNR on both sides of the frequency, so the derived values
+    /// like the far field break follow the frequency as well. A solved distance is kept, it is
+    /// not replaced by the slant range.
+    pub fn margin_frequency_sensitivity(&self) -> f64 {
+        let step = (self.frequency * 1e-4).max(1.0);
+        let low = (self.frequency - step).max(self.frequency / 2.0);
+        let high = self.frequency + step;
+        let margin = |frequency: f64| {
+            let mut budget = self.clone();
+            budget.slant_path = self.slant_elevation().is_some();
+            budget.target = Target::Snr;
+            budget.frequency = frequency;
+            budget.solve();
+            budget.link_margin()
+        };
+        (margin(high) - margin(low)) / (high - low)
+    }
+
+    /// Achieved SNR minus the SNR required by the receiver
+    pub fn link_margin(&self) -> f64 {
+        self.snr - self.required_snr
+    }
+
+    /// Margin the link would have in the given condition
+    pub fn link_margin_in(&self, condition: Condition) -> f64 {
+        self.link_margin() + self.losses_in(self.condition) - self.losses_in(condition)
+    }
+
+    /// System noise temperature, from the noise chain referred to the Rx antenna terminal or from
+    /// the temperature and noise figure of the parameters
+    pub fn system_temperature(&self) -> f64 {
+        if self.noise_chain {
+            calc::t_sys_at_antenna(
+                self.antenna_temperature,
+                self.feedline_loss,
+                self.feedline_temperature,
+                self.lna_noise_figure,
+            )
+        } else if self.separate_temperatures {
+            calc::system_noise_temperature(self.antenna_temperature, self.receiver_temperature)
+        } else {
+            // the temperature that gives the system noise floor, k T F B
+            self.temperature * f64::powf(10.0, self.noise_figure / 10.0)
+        }
+    }
+
+    pub fn g_over_t(&self) -> f64 {
+        calc::g_over_t(self.rx_antenna_gain, self.system_temperature())
+    }
+
+    /// Fraunhofer distance of the largest antenna aperture entered, of the aperture antennas and
+    /// the far field break, None without apertures
+    pub fn far_field_distance(&self) -> Option<f64> {
+        let break_aperture = self.break_at_far_field.then_some(self.break_aperture);
+        self.antennas
+            .values()
+            .map(|antenna| antenna.diameter)
+            .chain(break_aperture)
+            .filter(|aperture| *aperture > 0.0)
+            .max_by(f64::total_cmp)
+            .map(|aperture| calc::fraunhofer_distance(aperture, self.frequency, self.physics))
+    }
+
+    /// Noise figure in dB of the cascaded receive chain stages
+    pub fn cascade_noise_figure(&self) -> f64 {
+        let stages: Vec<(f64, f64)> = self.noise_stages.iter().map(|stage| (stage.gain, stage.noise_figure)).collect();
+        calc::noise::cascade(&stages)
+    }
+
+    /// Elevation angle of the satellite in degree, None for a terrestrial link or when the
+    /// distance is solved
+    pub fn slant_elevation(&self) -> Option<f64> {
+        (self.slant_path && self.target != Target::Distance).then_some(self.elevation)
+    }
+
+    pub fn data_rate_achievable(&self) -> bool {
+        self.data_rate > 0.0 && calc::shannon_required_snr(self.data_rate, self.bandwidth).is_finite()
+    }
+
+    /// Keep the values that follow from other inputs in sync: the cascaded noise figure, the
+    /// slant range, the far field break, the computed loss entries and the required SNR of the
+    /// data rate or the sensitivity
+    pub fn apply_derived(&mut self) {
+        self.apply_noise_cascade();
+        self.apply_slant_path();
+        self.apply_break_at_far_field();
+        self.apply_polarization_mismatch();
+        self.apply_fading_margin();
+        self.apply_data_rate();
+        self.apply_sensitivity();
+    }
+
+    /// Calculate the [`target`](LinkBudget::target) from the other values, store it and return
+    /// it. The derived values are brought in sync first. A budget without a finite solution returns NaN or infinity and
+    /// leaves the target unchanged.
+    pub fn solve(&mut self) -> f64 {
+        self.apply_derived();
+        let total = self.total_sum();
+        if !total.is_finite() {
+            return total;
+        }
+        let value = match self.target {
+            Target::Snr => { self.solve_snr() }
+            Target::Distance => { self.solve_distance() }
+            Target::TxPower => { self.solve_tx_power() }
+            Target::Bandwidth => { self.solve_bandwidth() }
+        };
+        if value.is_finite() {
+            match self.target {
+                Target::Snr => { self.snr = value }
+                Target::Distance => { self.distance = value }
+                Target::TxPower => { self.tx_power = value }
+                Target::Bandwidth if value > 0.0 => { self.bandwidth = value }
+                Target::Bandwidth => {}
+            }
+        }
+        value
+    }
+
+    fn apply_noise_cascade(&mut self) {
+        if self.noise_cascade {
+            self.noise_figure = self.cascade_noise_figure();
+        }
+    }
+
+    /// Set the distance to the slant range of the satellite
+    fn apply_slant_path(&mut self) {
+        if let Some(elevation) = self.slant_elevation() {
+            self.distance = calc::geometry::slant_range(self.orbit_altitude, elevation) * 1e3;
+        }
+    }
+
+    fn apply_break_at_far_field(&mut self) {
+        if self.break_at_far_field {
+            self.d_break = calc::fraunhofer_distance(self.break_aperture, self.frequency, self.physics);
+        }
+    }
+
+    /// Keep a computed entry of the losses in sync, None removes it. Only the value is updated,
+    /// the other settings of the entry are kept.
+    fn set_computed_loss(&mut self, name: &str, loss: Option<f64>) {
+        match loss {
+            Some(loss) => {
+                self.losses.entry(name.to_string()).or_insert_with(|| Loss::new(loss)).value = loss;
+            }
+            None => {
+                self.losses.shift_remove(name);
+            }
+        }
+    }
+
+    /// Keep the polarization mismatch entry of the losses in sync with the selection
+    fn apply_polarization_mismatch(&mut self) {
+        let loss = match self.polarization_mismatch {
+            PolarizationMismatch::None => { None }
+            PolarizationMismatch::LinearToLinear => { Some(calc::polarization_loss(self.polarization_tilt)) }
+            PolarizationMismatch::LinearToCircular => { Some(calc::LINEAR_TO_CIRCULAR_LOSS) }
+        };
+        self.set_computed_loss(POLARIZATION_LOSS_NAME, loss);
+    }
+
+    /// Keep the fading margin entry of the losses in sync with the availability
+    fn apply_fading_margin(&mut self) {
+        let availability = self.availability / 100.0;
+        let margin = match self.fading {
+            Fading::None => { None }
+            Fading::Rayleigh => { Some(calc::fading::rayleigh_margin(availability)) }
+            Fading::Rician => { Some(calc::fading::rician_margin(availability, self.k_factor)) }
+        };
+        self.set_computed_loss(FADING_MARGIN_NAME, margin);
+    }
+
+    /// Derive the required SNR from the target data rate, either at the Shannon limit or from the
+    /// required Eb/N0. Outside the Snr target the link is solved for exactly that SNR.
+    fn apply_data_rate(&mut self) {
+        if !self.use_data_rate || !self.data_rate_achievable() {
+            return;
+        }
+        self.required_snr = if self.use_ebn0 {
+            calc::ebn0_to_snr(self.required_ebn0, self.data_rate, self.bandwidth)
+        } else {
+            calc::shannon_required_snr(self.data_rate, self.bandwidth)
+        };
+        if self.target != Target::Snr {
+            self.snr = self.required_snr;
+        }
+    }
+
+    /// Derive the required SNR from the receiver sensitivity of a datasheet, outside the Snr
+    /// target the link is solved for exactly that SNR
+    fn apply_sensitivity(&mut self) {
+        if !self.use_sensitivity {
+            return;
+        }
+        self.required_snr = calc::sensitivity_to_snr(self.rx_sensitivity, self.noise_floor());
+        if self.target != Target::Snr {
+            self.snr = self.required_snr;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn environmental_and_equipment_losses() {
+        let mut budget = LinkBudget::default();
+        budget.losses.insert("cable".to_string(), Loss::new(2.0));
+        let mut rain_fade = Loss::new(6.0);
+        rain_fade.condition = LossCondition::FadedOnly;
+        budget.losses.insert("rain fade".to_string(), rain_fade);
+        budget.polarization_mismatch = PolarizationMismatch::LinearToCircular;
+        budget.apply_polarization_mismatch();
+        assert_eq!(budget.equipment_losses_in(Condition::Faded), 2.0);
+        assert_eq!(budget.environmental_losses_in(Condition::Clear), calc::LINEAR_TO_CIRCULAR_LOSS);
+        assert_eq!(budget.environmental_losses_in(Condition::Faded), calc::LINEAR_TO_CIRCULAR_LOSS + 6.0);
+    }
+
+    #[test]
+    fn slope_scaling() {
+        let mut loss = Loss::new(3.0);
+        loss.reference_frequency = 1e9;
+        loss.slope = 0.5;
+        loss.scaling = FrequencyScaling::PerOctave;
+        assert!((loss.value_at(4e9) - 4.0).abs() < 1e-12);
+        loss.scaling = FrequencyScaling::PerGhz;
+        assert!((loss.value_at(4e9) - 4.5).abs() < 1e-12);
+        assert_eq!(loss.value_at(1e9), 3.0);
+    }
+
+    #[test]
+    fn bandwidth_solution_balances_the_budget() {
+        let mut budget = LinkBudget::default();
+        budget.bandwidth = budget.solve_bandwidth();
+        assert!(budget.total_sum().abs() < 1e-9);
+
+        // the required SNR of the data rate follows the solved bandwidth
+        let mut budget = LinkBudget {
+            target: Target::Bandwidth,
+            use_data_rate: true,
+            data_rate: 1e3,
+            ..LinkBudget::default()
+        };
+        assert!(budget.solve().is_finite());
+        budget.apply_derived();
+        assert!((budget.required_snr - calc::shannon_required_snr(1e3, budget.bandwidth)).abs() < 1e-9);
+        assert!(budget.total_sum().abs() < 1e-6);
+
+        // with the Eb/N0 the bandwidth cancels out
+        budget.use_ebn0 = true;
+        assert!(budget.solve_bandwidth().is_nan());
+    }
+
+    #[test]
+    fn distance_solution_settles_with_excess_losses() {
+        let mut budget = LinkBudget {
+            target: Target::Distance,
+            atmosphere: true,
+            rain_rate: 10.0,
+            frequency: 20e9,
+            ..LinkBudget::default()
+        };
+        budget.solve();
+        assert!(budget.total_sum().abs() < 1e-6);
+    }
+
+    #[test]
+    fn relay_hops() {
+        let mut budget = LinkBudget { implementation_margin: 2.0, ..LinkBudget::default() };
+        assert_eq!(budget.hop_snrs().len(), 1);
+        assert!((budget.end_to_end_snr() - budget.solve_snr()).abs() < 1e-9);
+        // a relay that makes up the path loss of an identical hop repeats the first SNR
+        let relay_gain = budget.path_loss();
+        budget.hops.push(Hop { distance: budget.distance, relay_gain, noise_figure: budget.noise_figure, ..Hop::default() });
+        let snrs = budget.hop_snrs();
+        assert!((snrs[1] - snrs[0]).abs() < 1e-6);
+        assert!((budget.end_to_end_snr() - (snrs[0] - 10.0 * f64::log10(2.0) - 2.0)).abs() < 1e-6);
+        // a relay receiver with separate temperatures has the receiver temperature of its noise figure
+        budget.separate_temperatures = true;
+        budget.receiver_temperature = calc::noise_temperature(budget.hops[0].noise_figure);
+        let snrs = budget.hop_snrs();
+        assert!((snrs[1] - snrs[0]).abs() < 1e-6);
+    }
+
+    #[test]
+    fn low_elevation_attenuates_more() {
+        let mut budget = LinkBudget::default();
+        budget.rain_rate = 25.0;
+        budget.frequency = 20e9;
+        budget.slant_path = true;
+        budget.elevation = 90.0;
+        let zenith = budget.rain_loss();
+        budget.elevation = 10.0;
+        assert!(budget.rain_loss() > 5.0 * zenith);
+    }
+
+    #[test]
+    fn far_field_of_the_largest_aperture() {
+        let mut budget = LinkBudget::default();
+        assert!(budget.far_field_distance().is_none());
+        budget.antennas.insert("dish".to_string(), ApertureAntenna { diameter: 0.6, efficiency: 0.6 });
+        budget.antennas.insert("horn".to_string(), ApertureAntenna { diameter: 0.1, efficiency: 0.6 });
+        let expected = calc::fraunhofer_distance(0.6, budget.frequency, budget.physics);
+        assert!((budget.far_field_distance().unwrap() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn waterfall_reaches_the_margin() {
+        let mut budget = LinkBudget::default();
+        budget.gains.insert("antenna".to_string(), Gain::new(12.0));
+        budget.losses.insert("cable".to_string(), Loss::new(2.0));
+        budget.implementation_margin = 3.0;
+        budget.snr = budget.solve_snr();
+        let terms = budget.waterfall_terms();
+        assert_eq!(terms[0].0, "Tx power");
+        let sum: f64 = terms.iter().map(|(_, value)| value).sum();
+        assert!((sum - budget.link_margin()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn margin_sensitivity_follows_the_solution() {
+        // the path loss rises with 20 dB per decade of frequency
+        let budget = LinkBudget::default();
+        let free_space = -20.0 / (std::f64::consts::LN_10 * budget.frequency);
+        assert!((budget.margin_frequency_sensitivity() / free_space - 1.0).abs() < 1e-6);
+        // the far field break moves out with the frequency, less of the path has the steep exponent
+        let budget = LinkBudget { break_at_far_field: true, break_aperture: 10.0, ..LinkBudget::default() };
+        assert!(budget.margin_frequency_sensitivity() > free_space);
+        // the rain attenuation grows with the distance, the slope is the one at the solved distance
+        let mut budget = LinkBudget {
+            target: Target::Distance,
+            slant_path: true,
+            rain_rate: 10.0,
+            frequency: 20e9,
+            ..LinkBudget::default()
+        };
+        budget.solve();
+        let terrestrial = LinkBudget { slant_path: false, ..budget.clone() };
+        let sensitivity = budget.margin_frequency_sensitivity();
+        assert!((sensitivity / terrestrial.margin_frequency_sensitivity() - 1.0).abs() < 1e-9);
+    }
+}
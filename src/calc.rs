@@ -1,15 +1,101 @@
+use serde::{Deserialize, Serialize};
+
 const KB: f64 = 1.380649e-23; // Boltzmann constant (joule per kelvin)
 const C: f64 = 299792458.0;
-pub fn lambda(frequency: f64) -> f64 {
-    C / frequency
+const KB_TEXTBOOK: f64 = 1.38e-23;
+const C_TEXTBOOK: f64 = 3e8;
+
+/// Precision of the physical constants. The rounded textbook values reproduce textbook examples,
+/// they shift the noise floor by about 0.002 dB and wavelength based results (antenna gain) by
+/// about 0.006 dB per wavelength term, so differences up to a few hundredths of a dB are expected.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Constants {
+    #[default]
+    Exact,
+    Textbook,
+}
+
+/// Physical setting of a calculation, passed to the functions that depend on it
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Physics {
+    pub constants: Constants,
+    /// Refractive index of the medium the wave travels through, the inverse of the velocity
+    /// factor. It divides the speed of light in the wavelength and the propagation delay, values
+    /// below 1 are taken as vacuum.
+    pub refractive_index: f64,
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Self {
+            constants: Constants::default(),
+            refractive_index: 1.0,
+        }
+    }
+}
+
+impl Physics {
+    /// Boltzmann constant in joule per kelvin
+    pub fn boltzmann(self) -> f64 {
+        match self.constants {
+            Constants::Exact => { KB }
+            Constants::Textbook => { KB_TEXTBOOK }
+        }
+    }
+
+    /// Speed of light in vacuum in m/s
+    pub fn speed_of_light(self) -> f64 {
+        match self.constants {
+            Constants::Exact => { C }
+            Constants::Textbook => { C_TEXTBOOK }
+        }
+    }
+
+    /// Speed in m/s of the wave in the medium
+    pub fn propagation_speed(self) -> f64 {
+        self.speed_of_light() / self.refractive_index.max(1.0)
+    }
+}
+
+/// Wavelength in meter in the medium, the frequency must be positive, NaN is returned otherwise
+pub fn lambda(frequency: f64, physics: Physics) -> f64 {
+    if frequency <= 0.0 {
+        return f64::NAN;
+    }
+    physics.propagation_speed() / frequency
+}
+
+/// Frequency in Hertz of a wavelength in meter, the inverse of lambda
+pub fn frequency_from_lambda(lambda: f64, physics: Physics) -> f64 {
+    if lambda <= 0.0 {
+        return f64::NAN;
+    }
+    physics.propagation_speed() / lambda
 }
 
-pub fn thermal_noise_power(temperature: f64, bandwidth: f64) -> f64 {
-    KB * temperature * bandwidth
+pub fn thermal_noise_power(temperature: f64, bandwidth: f64, physics: Physics) -> f64 {
+    physics.boltzmann() * temperature * bandwidth
 }
 
-pub fn thermal_noise_temperature(power: f64, bandwidth: f64) -> f64 {
-    power / bandwidth / KB
+/// Noise floor in dBm of a receiver with the given noise figure, kTB plus the noise figure
+pub fn noise_floor_with_nf(temperature: f64, bandwidth: f64, noise_figure_db: f64, physics: Physics) -> f64 {
+    watt_to_dbm(thermal_noise_power(temperature, bandwidth, physics)) + noise_figure_db
+}
+
+/// Boltzmann constant in dBW/K/Hz
+pub fn boltzmann_db(physics: Physics) -> f64 {
+    10.0 * f64::log10(physics.boltzmann())
+}
+
+/// Minimum EIRP (dBW) plus G/T (dB/K) needed to reach the carrier to noise density cn0 (dBHz)
+/// over a path with the given losses
+pub fn required_eirp_plus_g_over_t(cn0_dbhz: f64, path_loss: f64, losses: f64, physics: Physics) -> f64 {
+    cn0_dbhz + path_loss + losses + boltzmann_db(physics)
+}
+
+pub fn thermal_noise_temperature(power: f64, bandwidth: f64, physics: Physics) -> f64 {
+    power / bandwidth / physics.boltzmann()
 }
 
 pub fn milliwatt_to_dbm(power: f64) -> f64 {
@@ -28,6 +114,73 @@ pub fn dbm_to_watt(dbm: f64) -> f64 {
     f64::powf(10.0, dbm / 10.) / 1000.0
 }
 
+/// Effective isotropic radiated power in dBm
+pub fn eirp(tx_power_dbm: f64, tx_antenna_gain_db: f64, tx_feed_loss_db: f64) -> f64 {
+    tx_power_dbm + tx_antenna_gain_db - tx_feed_loss_db
+}
+
+const METERS_PER_MILE: f64 = 1609.344;
+const METERS_PER_NAUTICAL_MILE: f64 = 1852.0;
+
+pub fn meters_to_kilometers(meters: f64) -> f64 {
+    meters / 1000.0
+}
+
+pub fn kilometers_to_meters(kilometers: f64) -> f64 {
+    kilometers * 1000.0
+}
+
+pub fn meters_to_miles(meters: f64) -> f64 {
+    meters / METERS_PER_MILE
+}
+
+pub fn miles_to_meters(miles: f64) -> f64 {
+    miles * METERS_PER_MILE
+}
+
+pub fn meters_to_nautical_miles(meters: f64) -> f64 {
+    meters / METERS_PER_NAUTICAL_MILE
+}
+
+pub fn nautical_miles_to_meters(nautical_miles: f64) -> f64 {
+    nautical_miles * METERS_PER_NAUTICAL_MILE
+}
+
+const ZERO_CELSIUS: f64 = 273.15; // Kelvin
+
+pub fn celsius_to_kelvin(celsius: f64) -> f64 {
+    celsius + ZERO_CELSIUS
+}
+
+pub fn kelvin_to_celsius(kelvin: f64) -> f64 {
+    kelvin - ZERO_CELSIUS
+}
+
+pub fn fahrenheit_to_kelvin(fahrenheit: f64) -> f64 {
+    celsius_to_kelvin((fahrenheit - 32.0) / 1.8)
+}
+
+pub fn kelvin_to_fahrenheit(kelvin: f64) -> f64 {
+    kelvin_to_celsius(kelvin) * 1.8 + 32.0
+}
+
+/// Power sum of two levels in dB, the larger one is factored out so that the linear values
+/// can not overflow
+pub fn db_add(a: f64, b: f64) -> f64 {
+    db_sum([a, b])
+}
+
+/// Power sum of levels in dB (log-sum-exp), see db_add
+pub fn db_sum(levels: impl IntoIterator<Item = f64>) -> f64 {
+    let levels: Vec<f64> = levels.into_iter().collect();
+    let max = levels.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if !max.is_finite() {
+        return max;
+    }
+    let sum: f64 = levels.iter().map(|level| f64::powf(10.0, (level - max) / 10.0)).sum();
+    max + 10.0 * f64::log10(sum)
+}
+
 pub fn dbm_to_dbw(dbm: f64) -> f64 {
     dbm - 30.0
 }
@@ -35,33 +188,1211 @@ pub fn dbm_to_dbw(dbm: f64) -> f64 {
 pub fn dbw_to_dbm(dbw: f64) -> f64 {
     dbw + 30.0
 }
+
+/// Impedance in Ohm of RF systems, the reference of voltage levels
+pub const SYSTEM_IMPEDANCE: f64 = 50.0;
+
+/// Voltage level in dBuV of a power in dBm across the impedance in Ohm, U^2 = P R
+pub fn dbm_to_dbuv(dbm: f64, impedance_ohms: f64) -> f64 {
+    dbm + 90.0 + 10.0 * f64::log10(impedance_ohms)
+}
+
+pub fn dbuv_to_dbm(dbuv: f64, impedance_ohms: f64) -> f64 {
+    dbuv - 90.0 - 10.0 * f64::log10(impedance_ohms)
+}
+
+/// Far field strength in V/m at the distance in meter from an antenna with the gain in dBi fed
+/// with the power in dBm, E = sqrt(30 P G) / d
+pub fn dbm_to_field_strength(dbm: f64, gain_db: f64, distance: f64) -> f64 {
+    f64::sqrt(30.0 * dbm_to_watt(dbm + gain_db)) / distance
+}
+
+/// Power in dBm that an antenna with the gain in dBi needs for the field strength in V/m at the
+/// distance in meter, inverse of dbm_to_field_strength
+pub fn field_strength_to_dbm(field_strength: f64, gain_db: f64, distance: f64) -> f64 {
+    watt_to_dbm((field_strength * distance).powi(2) / 30.0) - gain_db
+}
+
+/// One way propagation delay in seconds over the distance in meter at the speed in the medium
+pub fn propagation_delay(distance: f64, physics: Physics) -> f64 {
+    distance / physics.propagation_speed()
+}
+
+/// Radius in meter of the n-th Fresnel zone at d1 meter from the Tx and d2 meter from the Rx,
+/// sqrt(n lambda d1 d2 / (d1 + d2))
+pub fn fresnel_radius(n: u32, distance_d1: f64, distance_d2: f64, frequency: f64, physics: Physics) -> f64 {
+    f64::sqrt(n as f64 * lambda(frequency, physics) * distance_d1 * distance_d2 / (distance_d1 + distance_d2))
+}
+
+/// Far field (Fraunhofer) distance 2 D^2 / lambda of an antenna with the largest dimension aperture
+pub fn fraunhofer_distance(aperture: f64, frequency: f64, physics: Physics) -> f64 {
+    2.0 * aperture * aperture / lambda(frequency, physics)
+}
+
+/// Equivalent noise temperature in Kelvin of a noise figure in dB, referenced to 290 K
+pub fn noise_temperature(noise_figure_db: f64) -> f64 {
+    290.0 * (f64::powf(10.0, noise_figure_db / 10.0) - 1.0)
+}
+
+/// System noise temperature in Kelvin of an antenna and a receiver, T_sys = T_ant + T_rx. The
+/// antenna temperature is the noise it picks up, 30 K for the cold sky and 290 K for the ground.
+pub fn system_noise_temperature(t_antenna: f64, t_receiver: f64) -> f64 {
+    t_antenna + t_receiver
+}
+
+/// System noise temperature referred to the antenna terminal, for an antenna followed by a lossy
+/// feedline at a physical temperature and an LNA.
+/// T_sys = T_ant + (L - 1) * T_phys + L * T_lna
+pub fn t_sys_at_antenna(t_antenna: f64, feedline_loss_db: f64, t_physical: f64, lna_nf_db: f64) -> f64 {
+    let loss = f64::powf(10.0, feedline_loss_db / 10.0);
+    t_antenna + (loss - 1.0) * t_physical + loss * noise_temperature(lna_nf_db)
+}
+
+/// Receiver figure of merit G/T in dB/K. A system temperature that is not positive gives
+/// negative infinity instead of NaN.
+pub fn g_over_t(antenna_gain_db: f64, system_noise_temp_kelvin: f64) -> f64 {
+    if system_noise_temp_kelvin <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    antenna_gain_db - 10.0 * f64::log10(system_noise_temp_kelvin)
+}
+
+
+/// Largest polarization mismatch loss in dB, returned for orthogonal polarizations
+pub const MAX_POLARIZATION_LOSS: f64 = 60.0;
+
+/// Mismatch loss in dB between two linear polarizations tilted by the angle in degree,
+/// -20 log10(|cos(tilt)|). It is limited to MAX_POLARIZATION_LOSS, at 90 degree the loss of an
+/// ideal antenna would be infinite.
+pub fn polarization_loss(tilt_angle_deg: f64) -> f64 {
+    let cos = f64::abs(f64::cos(tilt_angle_deg.to_radians()));
+    (-20.0 * f64::log10(cos)).min(MAX_POLARIZATION_LOSS)
+}
+
+/// Mismatch loss in dB between a linear and a circular polarization
+pub const LINEAR_TO_CIRCULAR_LOSS: f64 = 3.0;
+
+/// Typical magnitudes in dB of losses that are often forgotten, a starting point for a new budget
+pub const COMMON_LOSSES: [(&str, f64); 7] = [
+    ("Implementation loss", 2.0),
+    ("Pointing loss", 1.0),
+    ("Atmospheric margin", 1.0),
+    ("Connector loss", 0.5),
+    ("Radome loss", 1.0),
+    ("Body loss", 3.0),
+    ("Foliage loss", 10.0),
+];
+
+/// Highest plausible gain in dB, even the largest radio telescopes stay below it
+pub const MAX_GAIN_DB: f64 = 80.0;
+
+/// Check a gain against physical limits, the error describes the violated rule
+pub fn validate_gain(db: f64) -> Result<(), String> {
+    if !db.is_finite() {
+        return Err("The gain is not a number".to_string());
+    }
+    if db > MAX_GAIN_DB {
+        return Err(format!("A gain above {MAX_GAIN_DB} dB is not physically plausible"));
+    }
+    Ok(())
+}
+
+/// Check a loss against physical limits, a negative loss is a gain and belongs in the gains
+pub fn validate_loss(db: f64) -> Result<(), String> {
+    if !db.is_finite() {
+        return Err("The loss is not a number".to_string());
+    }
+    if db < 0.0 {
+        return Err("A negative loss is a gain, add it as a gain instead".to_string());
+    }
+    Ok(())
+}
+
+/// Loss of a cable run in dB, the coefficient is the loss per meter at the frequency in use
+pub fn cable_loss(length_m: f64, loss_db_per_m: f64) -> f64 {
+    length_m * loss_db_per_m
+}
+
+/// Loss per meter of a cable in dB. The loss per 100 m is given at the reference frequency and
+/// scales with (frequency / reference_frequency)^frequency_exponent, 0.5 for a skin effect
+/// dominated coax, 0 for a loss independent of the frequency.
+pub fn cable_loss_per_meter(
+    loss_per_100m: f64,
+    reference_frequency: f64,
+    frequency_exponent: f64,
+    frequency: f64,
+) -> f64 {
+    let scaling = f64::powf(frequency / reference_frequency, frequency_exponent);
+    loss_per_100m * scaling / 100.0
+}
+
+/// Shannon capacity in bit/s. log2(1 + snr) is computed with ln_1p, so that it stays accurate at
+/// very low SNR where 1 + snr rounds to 1.
+pub fn shannon_capacity(bandwidth: f64, snr_db: f64) -> f64 {
+    let snr = f64::powf(10.0, snr_db / 10.0);
+    bandwidth * f64::ln_1p(snr) / std::f64::consts::LN_2
+}
+
+/// SNR in dB that the Shannon limit requires to carry data_rate (bit/s) in bandwidth (Hz)
+pub fn shannon_required_snr(data_rate: f64, bandwidth: f64) -> f64 {
+    let spectral_efficiency = data_rate / bandwidth;
+    10.0 * f64::log10(f64::exp_m1(spectral_efficiency * std::f64::consts::LN_2))
+}
+
+/// End to end SNR in dB of a link over amplify and forward relays from the SNRs of the hops. Each
+/// relay forwards the noise it received, so the noise to signal ratios add up.
+pub fn relay_snr(hop_snrs_db: &[f64]) -> f64 {
+    -db_sum(hop_snrs_db.iter().map(|snr| -snr))
+}
+
+/// SNR in dB the receiver needs to reach its sensitivity, both powers in dBm
+pub fn sensitivity_to_snr(sensitivity_dbm: f64, noise_floor_dbm: f64) -> f64 {
+    sensitivity_dbm - noise_floor_dbm
+}
+
+/// SNR in dB for an Eb/N0 in dB, SNR = Eb/N0 * data_rate / bandwidth
+pub fn ebn0_to_snr(ebn0_db: f64, data_rate: f64, bandwidth: f64) -> f64 {
+    ebn0_db + 10.0 * f64::log10(data_rate / bandwidth)
+}
+
+/// Eb/N0 in dB for an SNR in dB, the inverse of ebn0_to_snr
+pub fn snr_to_ebn0(snr_db: f64, data_rate: f64, bandwidth: f64) -> f64 {
+    snr_db - 10.0 * f64::log10(data_rate / bandwidth)
+}
+
+/// Normal distribution of an uncertain input in dB
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Normal {
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+/// Sample the sum of independent uncertain terms, one entry per sample. The generator is seeded
+/// with a constant so that repeated runs give the same result.
+pub fn monte_carlo(samples: usize, distributions: &[Normal]) -> Vec<f64> {
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    // xorshift64*, uniform in (0, 1]
+    let mut uniform = move || {
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        ((state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11) as f64 + 1.0) / (1u64 << 53) as f64
+    };
+    // Box-Muller, one standard normal value per pair of uniform values
+    let mut standard_normal = move || {
+        let radius = f64::sqrt(-2.0 * uniform().ln());
+        radius * f64::cos(2.0 * std::f64::consts::PI * uniform())
+    };
+    (0..samples)
+        .map(|_| distributions.iter().map(|d| d.mean + d.std_dev * standard_normal()).sum())
+        .collect()
+}
+
+/// Percentile (0..100) of the samples by the nearest rank, NaN without samples
+pub fn percentile(samples: &[f64], percent: f64) -> f64 {
+    if samples.is_empty() {
+        return f64::NAN;
+    }
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+pub mod antenna {
+    use super::{lambda, Physics};
+
+    /// Aperture efficiency of a typical parabolic dish
+    pub const DEFAULT_EFFICIENCY: f64 = 0.55;
+
+    /// Gain of a parabolic dish in dBi, 10 log10(efficiency (pi D / lambda)^2), for a given
+    /// diameter (meter) and aperture efficiency (0..1)
+    pub fn parabolic_gain(diameter_m: f64, frequency: f64, efficiency: f64, physics: Physics) -> f64 {
+        let circumference = std::f64::consts::PI * diameter_m / lambda(frequency, physics);
+        10.0 * f64::log10(efficiency * circumference * circumference)
+    }
+
+    /// Product of the half power beamwidths in square degree of an antenna with 0 dBi, the usual
+    /// approximation for antennas with moderate side lobes
+    pub const BEAMWIDTH_PRODUCT: f64 = 27000.0;
+
+    /// Approximate gain in dBi of an antenna with the half power beamwidths in degree,
+    /// 10 log10(27000 / (az el)). Beamwidths that are not positive give NaN.
+    pub fn gain_from_beamwidth(azimuth_deg: f64, elevation_deg: f64) -> f64 {
+        if azimuth_deg <= 0.0 || elevation_deg <= 0.0 {
+            return f64::NAN;
+        }
+        10.0 * f64::log10(BEAMWIDTH_PRODUCT / (azimuth_deg * elevation_deg))
+    }
+
+    /// Half power beamwidth in degree of a rotationally symmetric beam with the gain in dBi, the
+    /// inverse of gain_from_beamwidth
+    pub fn beamwidth_from_gain(gain_db: f64) -> f64 {
+        f64::sqrt(BEAMWIDTH_PRODUCT / f64::powf(10.0, gain_db / 10.0))
+    }
+
+    /// Gain in dBi at the angle in degree, linearly interpolated in a pattern table of (angle in
+    /// degree, gain in dBi) sorted by angle. The angle wraps around at 360 degree. A table that
+    /// covers the full circle, with a gap between its last and first angle no larger than its
+    /// steps, is interpolated across the gap, otherwise angles outside the table get the gain of
+    /// the nearer end. An empty table returns NaN.
+    pub fn interpolate_gain(table: &[(f64, f64)], angle: f64) -> f64 {
+        let (Some(&(first_angle, first_gain)), Some(&(last_angle, last_gain))) = (table.first(), table.last()) else {
+            return f64::NAN;
+        };
+        let angle = first_angle + (angle - first_angle).rem_euclid(360.0);
+        if let Some(i) = table.windows(2).position(|pair| angle <= pair[1].0) {
+            let ((a0, g0), (a1, g1)) = (table[i], table[i + 1]);
+            if a1 <= a0 {
+                return g1;
+            }
+            return g0 + (g1 - g0) * (angle - a0) / (a1 - a0);
+        }
+        let wrapped_first = first_angle + 360.0;
+        let gap = wrapped_first - last_angle;
+        let max_step = table.windows(2).map(|pair| pair[1].0 - pair[0].0).fold(0.0, f64::max);
+        if gap <= 0.0 {
+            last_gain
+        } else if gap <= max_step {
+            last_gain + (first_gain - last_gain) * (angle - last_angle) / gap
+        } else if angle - last_angle < wrapped_first - angle {
+            last_gain
+        } else {
+            first_gain
+        }
+    }
+}
+
+pub mod fading {
+    /// Largest fading margin in dB the solvers return
+    pub const MAX_MARGIN: f64 = 100.0;
+
+    /// Fading margin in dB for a Rayleigh fading channel, so that the received power stays above
+    /// the threshold with the availability (0..1). The outage probability is 1 - exp(-1/M).
+    pub fn rayleigh_margin(availability: f64) -> f64 {
+        (-10.0 * f64::log10(-f64::ln(availability))).clamp(0.0, MAX_MARGIN)
+    }
+
+    /// Probability that the power of a Rician channel with the K factor (linear) is below
+    /// threshold times the mean power. The non central chi-square distribution is evaluated as
+    /// a Poisson weighted sum of regularized lower incomplete gamma functions.
+    fn rician_outage(k_factor: f64, threshold: f64) -> f64 {
+        let y = (k_factor + 1.0) * threshold;
+        let terms = (k_factor + 10.0 * k_factor.sqrt() + 40.0) as usize;
+        let mut poisson = f64::exp(-k_factor); // e^-K K^n / n!
+        let mut outage = 0.0;
+        for n in 0..terms {
+            outage += poisson * lower_gamma(n as f64 + 1.0, y);
+            poisson *= k_factor / (n as f64 + 1.0);
+        }
+        outage
+    }
+
+    /// Regularized lower incomplete gamma function P(a, y) by its power series
+    fn lower_gamma(a: f64, y: f64) -> f64 {
+        if y <= 0.0 {
+            return 0.0;
+        }
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        let mut i = 1.0;
+        while term > sum * 1e-15 && i < 1000.0 {
+            term *= y / (a + i);
+            sum += term;
+            i += 1.0;
+        }
+        f64::exp(a * y.ln() - y - ln_gamma(a)) * sum
+    }
+
+    /// ln(Gamma(a)) for integer a, the only case needed here
+    fn ln_gamma(a: f64) -> f64 {
+        (2..a as u64).map(|i| (i as f64).ln()).sum()
+    }
+
+    /// Fading margin in dB for a Rician fading channel with the K factor (linear ratio of the
+    /// direct to the scattered power), for the availability (0..1). K = 0 is Rayleigh fading.
+    pub fn rician_margin(availability: f64, k_factor: f64) -> f64 {
+        let outage = 1.0 - availability;
+        if outage <= 0.0 {
+            return MAX_MARGIN;
+        }
+        let k_factor = k_factor.max(0.0);
+        // the outage falls with the margin, bisect over the margin in dB
+        let (mut low, mut high) = (-10.0, MAX_MARGIN);
+        for _ in 0..60 {
+            let mid = (low + high) / 2.0;
+            if rician_outage(k_factor, f64::powf(10.0, -mid / 10.0)) > outage {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        ((low + high) / 2.0).max(0.0)
+    }
+}
+
 pub mod friis {
-    
-    
-
-    pub fn path_loss(distance: f64, d_break: f64, frequency: f64, break_exponent: f64) -> f64 {
-        let one_meter_one_ghz = 32.0; // dB
-        let freq_loss = 20.0 * f64::log10(frequency / 1e9);
-        let path_loss = one_meter_one_ghz + freq_loss + if distance < d_break {
-            20.0 * f64::log10(distance / 1.0)
+    use super::{lambda, Physics};
+
+    /// Free space path loss in dB at 1 m and 1 GHz, the reference of the model
+    pub const DEFAULT_REFERENCE_LOSS: f64 = 32.44;
+
+    /// Smallest break distance in meter, a break at 0 divides by zero
+    pub const MIN_D_BREAK: f64 = 1e-3;
+    /// Smallest break exponent, below free space the loss would grow slower after the break
+    pub const MIN_BREAK_EXPONENT: f64 = 2.0;
+    /// Largest break exponent the user interface accepts, dense clutter
+    pub const MAX_BREAK_EXPONENT: f64 = 6.0;
+    /// Shortest distance in meter the inverse returns, the 1 m reference of the model
+    pub const MIN_DISTANCE: f64 = 1.0;
+
+    /// Break distance and exponent clamped to physical values
+    pub fn clamp_break(d_break: f64, break_exponent: f64) -> (f64, f64) {
+        (d_break.max(MIN_D_BREAK), break_exponent.max(MIN_BREAK_EXPONENT))
+    }
+
+    /// Exact free space path loss 20 log10(4 pi / lambda) in dB at 1 m and 1 GHz with the
+    /// selected constants
+    pub fn free_space_reference(physics: Physics) -> f64 {
+        20.0 * f64::log10(4.0 * std::f64::consts::PI / lambda(1e9, physics))
+    }
+
+    /// Loss in dB at 1 m and the frequency, the reference moved from 1 GHz with 20 log10(f)
+    fn loss_at_one_meter(frequency: f64, reference_loss: f64) -> f64 {
+        reference_loss + 20.0 * f64::log10(frequency / 1e9)
+    }
+
+    /// Loss in dB from 1 m to the distance, 20 dB per decade up to the break and 10 n dB per
+    /// decade beyond it. Both slopes meet at the break, so the loss is continuous.
+    fn distance_loss(distance: f64, d_break: f64, break_exponent: f64) -> f64 {
+        if distance < d_break {
+            20.0 * f64::log10(distance)
         } else {
-            20.0 * f64::log10(d_break / 1.0) + break_exponent * 10.0 * f64::log10(distance / d_break)
-        };
+            20.0 * f64::log10(d_break) + break_exponent * 10.0 * f64::log10(distance / d_break)
+        }
+    }
+
+    /// Inverse of distance_loss, the same branch is taken on both sides of the break
+    fn distance_for_loss(loss: f64, d_break: f64, break_exponent: f64) -> f64 {
+        let loss_at_break = 20.0 * f64::log10(d_break);
+        if loss < loss_at_break {
+            f64::powf(10.0, loss / 20.0)
+        } else {
+            d_break * f64::powf(10.0, (loss - loss_at_break) / (10.0 * break_exponent))
+        }
+    }
+
+    /// Path loss in dB, the frequency must be positive, NaN is returned otherwise. The break
+    /// distance and exponent are clamped with clamp_break. reference_loss is the loss in dB at
+    /// 1 m and 1 GHz.
+    pub fn path_loss(distance: f64, d_break: f64, frequency: f64, break_exponent: f64, reference_loss: f64) -> f64 {
+        if frequency <= 0.0 {
+            return f64::NAN;
+        }
+        let (d_break, break_exponent) = clamp_break(d_break, break_exponent);
+        loss_at_one_meter(frequency, reference_loss) + distance_loss(distance, d_break, break_exponent)
+    }
+
+    /// Inverse of path_loss with the same reference, the frequency must be positive, NaN is
+    /// returned otherwise. A path loss below the loss at 1 m would need a distance in the near
+    /// field of the antennas where the far field model does not hold, MIN_DISTANCE is returned
+    /// instead and the link does not close at that distance.
+    pub fn distance(path_loss: f64, d_break: f64, frequency: f64, break_exponent: f64, reference_loss: f64) -> f64 {
+        if frequency <= 0.0 {
+            return f64::NAN;
+        }
+        let (d_break, break_exponent) = clamp_break(d_break, break_exponent);
+        let loss = path_loss - loss_at_one_meter(frequency, reference_loss);
+        let distance = distance_for_loss(loss, d_break, break_exponent);
+        if distance < MIN_DISTANCE {
+            return MIN_DISTANCE;
+        }
+        distance
+    }
+}
+
+pub mod geometry {
+    /// Mean earth radius in km
+    pub const EARTH_RADIUS_KM: f64 = 6371.0;
+
+    /// Slant range in km from a ground station to a satellite at the altitude in km above the
+    /// spherical earth, seen at the elevation angle in degree
+    pub fn slant_range(altitude_km: f64, elevation_deg: f64) -> f64 {
+        let orbit_radius = EARTH_RADIUS_KM + altitude_km;
+        let (sin, cos) = elevation_deg.to_radians().sin_cos();
+        f64::sqrt(orbit_radius * orbit_radius - (EARTH_RADIUS_KM * cos).powi(2)) - EARTH_RADIUS_KM * sin
+    }
+
+    /// Path length through the atmosphere relative to the zenith at the elevation angle in
+    /// degree. It is about 1 / sin(elevation) above 10 degree, the fit of Kasten and Young keeps
+    /// it finite towards the horizon.
+    pub fn airmass(elevation_deg: f64) -> f64 {
+        let elevation = elevation_deg.clamp(0.0, 90.0);
+        1.0 / (elevation.to_radians().sin() + 0.50572 * (elevation + 6.07995).powf(-1.6364))
+    }
+}
+
+pub mod two_ray {
+    use super::{lambda, Physics};
+
+    /// Distance beyond which the direct and the ground reflected ray no longer interfere
+    /// constructively, 4 pi h_t h_r / lambda
+    pub fn crossover_distance(tx_height: f64, rx_height: f64, frequency: f64, physics: Physics) -> f64 {
+        4.0 * std::f64::consts::PI * tx_height * rx_height / lambda(frequency, physics)
+    }
 
-        return path_loss;
+    /// Two ray ground reflection path loss in dB, the coherent sum of the direct and the ground
+    /// reflected ray (reflection coefficient -1) below the crossover distance, and the
+    /// 40 log10(d) asymptote beyond it
+    pub fn path_loss(distance: f64, tx_height: f64, rx_height: f64, frequency: f64, physics: Physics) -> f64 {
+        if frequency <= 0.0 {
+            return f64::NAN;
+        }
+        if distance >= crossover_distance(tx_height, rx_height, frequency, physics) {
+            return 40.0 * f64::log10(distance) - 20.0 * f64::log10(tx_height * rx_height);
+        }
+
+        let lambda = lambda(frequency, physics);
+        let k = 2.0 * std::f64::consts::PI / lambda;
+        let d_direct = f64::hypot(distance, tx_height - rx_height);
+        let d_reflected = f64::hypot(distance, tx_height + rx_height);
+        let re = f64::cos(k * d_direct) / d_direct - f64::cos(k * d_reflected) / d_reflected;
+        let im = f64::sin(k * d_direct) / d_direct - f64::sin(k * d_reflected) / d_reflected;
+        let amplitude = lambda / (4.0 * std::f64::consts::PI) * f64::hypot(re, im);
+        -20.0 * f64::log10(amplitude)
+    }
+
+    /// Inverse of path_loss. Below the crossover distance the loss is not monotonic, the largest
+    /// distance with the requested loss is returned, NaN if there is none.
+    pub fn distance(path_loss: f64, tx_height: f64, rx_height: f64, frequency: f64, physics: Physics) -> f64 {
+        if frequency <= 0.0 {
+            return f64::NAN;
+        }
+        let crossover = crossover_distance(tx_height, rx_height, frequency, physics);
+        let asymptotic = 10f64.powf((path_loss + 20.0 * f64::log10(tx_height * rx_height)) / 40.0);
+        if asymptotic >= crossover {
+            return asymptotic;
+        }
+
+        // walk down from the crossover distance in small log steps and bisect the first crossing
+        let error = |d: f64| self::path_loss(d, tx_height, rx_height, frequency, physics) - path_loss;
+        let steps_per_decade = 2000.0;
+        let ratio = 10f64.powf(-1.0 / steps_per_decade);
+        let min_distance = 1e-3;
+        let mut high = crossover * (1.0 - 1e-12);
+        let mut error_high = error(high);
+        while high > min_distance {
+            let low = high * ratio;
+            let error_low = error(low);
+            if error_low.signum() != error_high.signum() {
+                let (mut a, mut b) = (low, high);
+                for _ in 0..60 {
+                    let mid = 0.5 * (a + b);
+                    if error(mid).signum() == error_low.signum() {
+                        a = mid;
+                    } else {
+                        b = mid;
+                    }
+                }
+                return 0.5 * (a + b);
+            }
+            high = low;
+            error_high = error_low;
+        }
+        f64::NAN
+    }
+}
+
+/// Attenuation by atmospheric gases, after the simplified line model of ITU-R P.676 (Annex 2),
+/// valid from 1 to 350 GHz
+pub mod atmospheric {
+    fn phi(rp: f64, rt: f64, a: f64, b: f64, c: f64, d: f64) -> f64 {
+        rp.powf(a) * rt.powf(b) * f64::exp(c * (1.0 - rp) + d * (1.0 - rt))
     }
 
-    pub fn distance(path_loss: f64, d_break: f64, frequency: f64, break_exponent: f64) -> f64 {
-        let one_meter_one_ghz = 32.0; // dB
-        let freq_loss = 20.0 * f64::log10(frequency / 1e9);
-        let path_loss = path_loss - one_meter_one_ghz - freq_loss;
-        let loss_at_break = 20.0 * f64::log10(d_break / 1.0);
+    /// Specific attenuation of dry air (oxygen) in dB/km, f in GHz
+    pub fn oxygen_attenuation(f: f64, rp: f64, rt: f64) -> f64 {
+        let g54 = 2.192 * phi(rp, rt, 1.8286, -1.9487, 0.4051, -2.8509);
+        let g58 = 12.59 * phi(rp, rt, 1.0045, 3.5610, 0.1588, 1.2834);
+        let g60 = 15.0 * phi(rp, rt, 0.9003, 4.1335, 0.0427, 1.6088);
+        let g62 = 14.28 * phi(rp, rt, 0.9886, 3.4176, 0.1827, 1.3429);
+        let g64 = 6.819 * phi(rp, rt, 1.4320, 0.6258, 0.3177, -0.5914);
+        let g66 = 1.908 * phi(rp, rt, 2.0717, -4.1404, 0.4910, -4.8718);
 
-        let distance = if path_loss <= loss_at_break {
-            10f64.powf(path_loss / 20.0)
+        if f <= 54.0 {
+            let xi1 = phi(rp, rt, 0.0717, -1.8132, 0.0156, -1.6515);
+            let xi2 = phi(rp, rt, 0.5146, -4.6368, -0.1921, -5.7416);
+            let xi3 = phi(rp, rt, 0.3414, -6.5851, 0.2130, -8.5854);
+            (7.2 * rt.powf(2.8) / (f * f + 0.34 * rp * rp * rt.powf(1.6))
+                + 0.62 * xi3 / ((54.0 - f).powf(1.16 * xi1) + 0.83 * xi2))
+                * f * f * rp * rp * 1e-3
+        } else if f <= 60.0 {
+            f64::exp(
+                g54.ln() / 24.0 * (f - 58.0) * (f - 60.0) - g58.ln() / 8.0 * (f - 54.0) * (f - 60.0)
+                    + g60.ln() / 12.0 * (f - 54.0) * (f - 58.0),
+            )
+        } else if f <= 62.0 {
+            g60 + (g62 - g60) * (f - 60.0) / 2.0
+        } else if f <= 66.0 {
+            f64::exp(
+                g62.ln() / 8.0 * (f - 64.0) * (f - 66.0) - g64.ln() / 4.0 * (f - 62.0) * (f - 66.0)
+                    + g66.ln() / 8.0 * (f - 62.0) * (f - 64.0),
+            )
+        } else if f <= 120.0 {
+            let xi4 = phi(rp, rt, -0.0112, 0.0092, -0.1033, -0.0009);
+            let xi5 = phi(rp, rt, 0.2705, -2.7192, -0.3016, -4.1033);
+            let xi6 = phi(rp, rt, 0.2445, -5.9191, 0.0422, -8.0719);
+            let xi7 = phi(rp, rt, -0.1833, 6.5589, -0.2402, 6.131);
+            (3.02e-4 * rt.powf(3.5)
+                + 0.283 * rt.powf(3.8) / ((f - 118.75).powi(2) + 2.91 * rp * rp * rt.powf(1.6))
+                + 0.502 * xi6 * (1.0 - 0.0163 * xi7 * (f - 66.0))
+                    / ((f - 66.0).powf(1.4346 * xi4) + 1.15 * xi5))
+                * f * f * rp * rp * 1e-3
         } else {
-            10f64.powf((path_loss - loss_at_break) / break_exponent / 10.0) * d_break
+            let delta = -0.00306 * phi(rp, rt, 3.211, -14.94, 1.583, -16.37);
+            (3.02e-4 / (1.0 + 1.9e-5 * f.powf(1.5))
+                + 0.283 * rt.powf(0.3) / ((f - 118.75).powi(2) + 2.91 * rp * rp * rt.powf(1.6)))
+                * f * f * rp * rp * rt.powf(3.5) * 1e-3
+                + delta
+        }
+    }
+
+    /// Specific attenuation of water vapour in dB/km, f in GHz, rho in g/m^3
+    pub fn water_vapour_attenuation(f: f64, rp: f64, rt: f64, rho: f64) -> f64 {
+        let eta1 = 0.955 * rp * rt.powf(0.68) + 0.006 * rho;
+        let eta2 = 0.735 * rp * rt.powf(0.5) + 0.0353 * rt.powi(4) * rho;
+        let g = |fi: f64| 1.0 + ((f - fi) / (f + fi)).powi(2);
+        let line = |a: f64, b: f64, fi: f64, w: f64| {
+            a * eta1 * f64::exp(b * (1.0 - rt)) / ((f - fi).powi(2) + w * eta1 * eta1)
+        };
+        let far_line = |a: f64, eta: f64, b: f64, fi: f64| a * eta * f64::exp(b * (1.0 - rt)) / (f - fi).powi(2);
+
+        (line(3.98, 2.23, 22.235, 9.42) * g(22.0)
+            + line(11.96, 0.7, 183.31, 11.14)
+            + line(0.081, 6.44, 321.226, 6.29)
+            + line(3.66, 1.6, 325.153, 9.22)
+            + far_line(25.37, eta1, 1.09, 380.0)
+            + far_line(17.4, eta1, 1.46, 448.0)
+            + far_line(844.6, eta1, 0.17, 557.0) * g(557.0)
+            + far_line(290.0, eta1, 0.41, 752.0) * g(752.0)
+            + far_line(8.3328e4, eta2, 0.99, 1780.0) * g(1780.0))
+            * f * f * rt.powf(2.5) * rho * 1e-4
+    }
+
+    /// Gas absorption in dB over a horizontal path. The frequency is in Hertz, the distance in
+    /// meter, the temperature in Kelvin and the water vapour density in g/m^3.
+    pub fn gas_loss(frequency: f64, distance: f64, temperature: f64, pressure_hpa: f64, water_vapor_density: f64) -> f64 {
+        let f = frequency / 1e9;
+        let rp = pressure_hpa / 1013.0;
+        let rt = 288.0 / (temperature - 0.15); // 288 / (273 + t in Celsius)
+        let specific = oxygen_attenuation(f, rp, rt) + water_vapour_attenuation(f, rp, rt, water_vapor_density);
+        specific.max(0.0) * distance / 1000.0
+    }
+
+    /// Equivalent heights in km of the oxygen and the water vapour, ITU-R P.676 away from the
+    /// absorption lines
+    const OXYGEN_HEIGHT: f64 = 6.0;
+    const WATER_VAPOUR_HEIGHT: f64 = 1.6;
+
+    /// Gas absorption in dB through the whole atmosphere towards the zenith, the surface
+    /// conditions as for gas_loss
+    pub fn zenith_gas_loss(frequency: f64, temperature: f64, pressure_hpa: f64, water_vapor_density: f64) -> f64 {
+        let f = frequency / 1e9;
+        let rp = pressure_hpa / 1013.0;
+        let rt = 288.0 / (temperature - 0.15);
+        let oxygen = oxygen_attenuation(f, rp, rt).max(0.0) * OXYGEN_HEIGHT;
+        oxygen + water_vapour_attenuation(f, rp, rt, water_vapor_density).max(0.0) * WATER_VAPOUR_HEIGHT
+    }
+}
+
+pub mod rain {
+    use std::fmt::{Display, Formatter};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum Polarization {
+        #[default]
+        Horizontal,
+        Vertical,
+        Circular,
+    }
+
+    impl Display for Polarization {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Polarization::Horizontal => { write!(f, "Horizontal") }
+                Polarization::Vertical => { write!(f, "Vertical") }
+                Polarization::Circular => { write!(f, "Circular") }
+            }
+        }
+    }
+
+    /// ITU-R P.838 regression coefficients: frequency in GHz, k_H, alpha_H, k_V, alpha_V
+    const COEFFICIENTS: [(f64, f64, f64, f64, f64); 26] = [
+        (1.0, 0.0000387, 0.912, 0.0000352, 0.880),
+        (2.0, 0.000154, 0.963, 0.000138, 0.923),
+        (4.0, 0.000650, 1.121, 0.000591, 1.075),
+        (6.0, 0.00175, 1.308, 0.00155, 1.265),
+        (7.0, 0.00301, 1.332, 0.00265, 1.312),
+        (8.0, 0.00454, 1.327, 0.00395, 1.310),
+        (10.0, 0.0101, 1.276, 0.00887, 1.264),
+        (12.0, 0.0188, 1.217, 0.0168, 1.200),
+        (15.0, 0.0367, 1.154, 0.0335, 1.128),
+        (20.0, 0.0751, 1.099, 0.0691, 1.065),
+        (25.0, 0.124, 1.061, 0.113, 1.030),
+        (30.0, 0.187, 1.021, 0.167, 1.000),
+        (35.0, 0.263, 0.979, 0.233, 0.963),
+        (40.0, 0.350, 0.939, 0.310, 0.929),
+        (45.0, 0.442, 0.903, 0.393, 0.897),
+        (50.0, 0.536, 0.873, 0.479, 0.868),
+        (60.0, 0.707, 0.826, 0.642, 0.824),
+        (70.0, 0.851, 0.793, 0.784, 0.793),
+        (80.0, 0.975, 0.769, 0.906, 0.769),
+        (90.0, 1.06, 0.753, 0.999, 0.754),
+        (100.0, 1.12, 0.743, 1.06, 0.744),
+        (120.0, 1.18, 0.731, 1.13, 0.732),
+        (150.0, 1.31, 0.710, 1.27, 0.711),
+        (200.0, 1.45, 0.689, 1.42, 0.690),
+        (300.0, 1.36, 0.688, 1.35, 0.689),
+        (400.0, 1.32, 0.683, 1.31, 0.684),
+    ];
+
+    /// k and alpha at f in GHz, k is interpolated on a log-log scale, alpha linearly over log f.
+    /// Outside of the table the closest entry is used.
+    fn coefficients(f: f64, polarization: Polarization) -> (f64, f64) {
+        let first = COEFFICIENTS[0];
+        let last = COEFFICIENTS[COEFFICIENTS.len() - 1];
+        let f = f.clamp(first.0, last.0);
+        let i = COEFFICIENTS.iter().position(|c| c.0 >= f).unwrap_or(COEFFICIENTS.len() - 1).max(1);
+        let (f0, kh0, ah0, kv0, av0) = COEFFICIENTS[i - 1];
+        let (f1, kh1, ah1, kv1, av1) = COEFFICIENTS[i];
+        let t = (f / f0).ln() / (f1 / f0).ln();
+        let k = |k0: f64, k1: f64| f64::exp(k0.ln() + t * (k1.ln() - k0.ln()));
+        let a = |a0: f64, a1: f64| a0 + t * (a1 - a0);
+
+        let (kh, ah) = (k(kh0, kh1), a(ah0, ah1));
+        let (kv, av) = (k(kv0, kv1), a(av0, av1));
+        match polarization {
+            Polarization::Horizontal => { (kh, ah) }
+            Polarization::Vertical => { (kv, av) }
+            Polarization::Circular => {
+                let k = (kh + kv) / 2.0;
+                (k, (kh * ah + kv * av) / (2.0 * k))
+            }
+        }
+    }
+
+    /// Specific rain attenuation in dB/km, the frequency is in Hertz
+    pub fn specific_attenuation(frequency: f64, rain_rate_mm_per_hr: f64, polarization: Polarization) -> f64 {
+        if frequency <= 0.0 || rain_rate_mm_per_hr <= 0.0 {
+            return 0.0;
+        }
+        let (k, alpha) = coefficients(frequency / 1e9, polarization);
+        k * rain_rate_mm_per_hr.powf(alpha)
+    }
+
+    /// Rain attenuation in dB over a terrestrial path of the given distance in meter. Rain cells
+    /// are not uniform over long paths, the path is shortened by the reduction factor of ITU-R P.530.
+    pub fn path_attenuation(frequency: f64, distance: f64, rain_rate_mm_per_hr: f64, polarization: Polarization) -> f64 {
+        let d = distance / 1000.0;
+        let d0 = 35.0 * f64::exp(-0.015 * rain_rate_mm_per_hr.min(100.0));
+        let effective_length = d / (1.0 + d / d0);
+        specific_attenuation(frequency, rain_rate_mm_per_hr, polarization) * effective_length
+    }
+
+    /// Height in km of the rain above the ground station, ITU-R P.839 at mid latitudes
+    pub const RAIN_HEIGHT: f64 = 5.0;
+
+    /// Rain attenuation in dB towards the zenith, through the rain height
+    pub fn zenith_attenuation(frequency: f64, rain_rate_mm_per_hr: f64, polarization: Polarization) -> f64 {
+        specific_attenuation(frequency, rain_rate_mm_per_hr, polarization) * RAIN_HEIGHT
+    }
+}
+
+pub mod noise {
+    /// Noise figure in dB of cascaded stages, (gain, noise figure) in dB in signal order, after
+    /// the Friis formula for noise F = F1 + (F2 - 1) / G1 + (F3 - 1) / (G1 G2) + ... A passive
+    /// loss is a stage with the negative loss as gain and the loss as noise figure. No stages
+    /// give 0 dB.
+    pub fn cascade(stages: &[(f64, f64)]) -> f64 {
+        let mut factor = 1.0;
+        let mut gain = 1.0;
+        for (gain_db, noise_figure_db) in stages {
+            factor += (f64::powf(10.0, noise_figure_db / 10.0) - 1.0) / gain;
+            gain *= f64::powf(10.0, gain_db / 10.0);
+        }
+        10.0 * f64::log10(factor)
+    }
+}
+
+/// Loss of common feeders over the frequency. Coax follows a fit of the conductor loss, growing
+/// with the square root of the frequency, plus the dielectric loss, growing linearly. Copper
+/// waveguides use the conductor loss of the dominant mode and carry nothing below the cutoff.
+pub mod feeder {
+    use std::f64::consts::PI;
+    use std::fmt::{Display, Formatter};
+    use serde::{Deserialize, Serialize};
+
+    const COPPER_CONDUCTIVITY: f64 = 5.8e7; // S/m
+    const MU_0: f64 = 4e-7 * PI;            // H/m
+    const ETA_0: f64 = 376.730313;          // Ohm
+    const NEPER_TO_DB: f64 = 8.685889638;
+    const TE11_ROOT: f64 = 1.841184; // first root of the derivative of J1
+
+    const WR90_A: f64 = 22.86e-3;       // meter, broad wall
+    const WR90_B: f64 = 10.16e-3;       // meter, narrow wall
+    const WC109_RADIUS: f64 = 13.894e-3; // meter
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum FeederType {
+        Rg58,
+        Rg213,
+        Lmr400,
+        Wr90,  // rectangular waveguide, TE10
+        Wc109, // circular waveguide, TE11
+    }
+
+    impl FeederType {
+        pub const ALL: [FeederType; 5] =
+            [FeederType::Rg58, FeederType::Rg213, FeederType::Lmr400, FeederType::Wr90, FeederType::Wc109];
+
+        /// Lowest frequency in Hertz the feeder carries, zero for coax. It follows from the
+        /// dimensions of the waveguide, the exact speed of light is used.
+        pub fn cutoff_frequency(self) -> f64 {
+            match self {
+                FeederType::Rg58 | FeederType::Rg213 | FeederType::Lmr400 => { 0.0 }
+                FeederType::Wr90 => { super::C / (2.0 * WR90_A) }
+                FeederType::Wc109 => { TE11_ROOT * super::C / (2.0 * PI * WC109_RADIUS) }
+            }
+        }
+    }
+
+    impl Display for FeederType {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match self {
+                FeederType::Rg58 => { write!(f, "RG-58") }
+                FeederType::Rg213 => { write!(f, "RG-213") }
+                FeederType::Lmr400 => { write!(f, "LMR-400") }
+                FeederType::Wr90 => { write!(f, "WR-90") }
+                FeederType::Wc109 => { write!(f, "WC-109") }
+            }
+        }
+    }
+
+    /// Coax loss in dB per 100 m, k1 sqrt(f / MHz) + k2 f / MHz fitted to the data sheets
+    fn coax_loss_per_100m(k1: f64, k2: f64, frequency: f64) -> f64 {
+        let f_mhz = frequency / 1e6;
+        k1 * f64::sqrt(f_mhz) + k2 * f_mhz
+    }
+
+    /// Loss in dB of a feeder of length meter at the frequency, infinite at and below the cutoff
+    /// of a waveguide. The frequency must be positive, NaN is returned otherwise.
+    pub fn loss(feeder: FeederType, length: f64, frequency: f64) -> f64 {
+        if frequency <= 0.0 {
+            return f64::NAN;
+        }
+        let cutoff = feeder.cutoff_frequency();
+        if frequency <= cutoff {
+            return f64::INFINITY;
+        }
+        let ratio = (cutoff / frequency).powi(2);
+        let surface_resistance = f64::sqrt(PI * frequency * MU_0 / COPPER_CONDUCTIVITY);
+        let db_per_meter = match feeder {
+            FeederType::Rg58 => { coax_loss_per_100m(1.375, 0.02252, frequency) / 100.0 }
+            FeederType::Rg213 => { coax_loss_per_100m(0.566, 0.00541, frequency) / 100.0 }
+            FeederType::Lmr400 => { coax_loss_per_100m(0.385, 0.001218, frequency) / 100.0 }
+            FeederType::Wr90 => {
+                let alpha = surface_resistance / (ETA_0 * WR90_B * f64::sqrt(1.0 - ratio))
+                    * (1.0 + 2.0 * WR90_B / WR90_A * ratio);
+                NEPER_TO_DB * alpha
+            }
+            FeederType::Wc109 => {
+                let alpha = surface_resistance / (ETA_0 * WC109_RADIUS * f64::sqrt(1.0 - ratio))
+                    * (ratio + 1.0 / (TE11_ROOT * TE11_ROOT - 1.0));
+                NEPER_TO_DB * alpha
+            }
         };
-        return distance;
+        length * db_per_meter
+    }
+}
+
+pub mod diffraction {
+    use super::{lambda, Physics};
+
+    /// Fresnel-Kirchhoff diffraction parameter v = h sqrt(2 (d1 + d2) / (lambda d1 d2)) of an
+    /// obstacle reaching height meter above the line of sight, d1 meter from the Tx and d2 meter
+    /// from the Rx. Negative heights are below the line of sight.
+    pub fn parameter(height: f64, distance_d1: f64, distance_d2: f64, frequency: f64, physics: Physics) -> f64 {
+        let lambda = lambda(frequency, physics);
+        height * f64::sqrt(2.0 * (distance_d1 + distance_d2) / (lambda * distance_d1 * distance_d2))
+    }
+
+    /// Loss in dB of a single knife edge with the diffraction parameter v, the approximation of
+    /// ITU-R P.526. Obstacles with v below -0.78 leave enough clearance to cause no loss.
+    pub fn knife_edge(v: f64) -> f64 {
+        if v <= -0.78 {
+            return 0.0;
+        }
+        6.9 + 20.0 * f64::log10(f64::sqrt((v - 0.1).powi(2) + 1.0) + v - 0.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHYSICS: Physics = Physics { constants: Constants::Exact, refractive_index: 1.0 };
+
+    #[test]
+    fn lambda_requires_positive_frequency() {
+        assert!(lambda(0.0, PHYSICS).is_nan());
+        assert!(lambda(-1.0, PHYSICS).is_nan());
+        assert!(frequency_from_lambda(0.0, PHYSICS).is_nan());
+        assert!((frequency_from_lambda(lambda(2.4e9, PHYSICS), PHYSICS) - 2.4e9).abs() < 1e-3);
+        assert!(friis::path_loss(100.0, 500.0, 0.0, 4.3, friis::DEFAULT_REFERENCE_LOSS).is_nan());
+    }
+
+    #[test]
+    fn power_conversions() {
+        assert_eq!(milliwatt_to_dbm(1.0), 0.0);
+        assert!((milliwatt_to_dbm(1000.0) - 30.0).abs() < 1e-12);
+        assert!((watt_to_dbm(1.0) - 30.0).abs() < 1e-12);
+        assert!((dbm_to_milliwat(20.0) - 100.0).abs() < 1e-9);
+        assert!((dbm_to_dbw(30.0)).abs() < 1e-12);
+        assert!((dbw_to_dbm(dbm_to_dbw(-97.3)) + 97.3).abs() < 1e-12);
+        for dbm in [-174.0, -90.0, 0.0, 13.0, 60.0] {
+            assert!((watt_to_dbm(dbm_to_watt(dbm)) - dbm).abs() < 1e-9, "{dbm}");
+        }
+    }
+
+    #[test]
+    fn length_conversions() {
+        assert_eq!(meters_to_kilometers(kilometers_to_meters(3.5)), 3.5);
+        assert!((miles_to_meters(1.0) - 1609.344).abs() < 1e-9);
+        assert!((meters_to_miles(miles_to_meters(2.5)) - 2.5).abs() < 1e-12);
+        assert!((meters_to_nautical_miles(nautical_miles_to_meters(7.0)) - 7.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn thermal_noise_inverse() {
+        let density = watt_to_dbm(thermal_noise_power(290.0, 1.0, PHYSICS));
+        assert!((density + 173.98).abs() < 0.01);
+        for (temperature, bandwidth) in [(290.0, 1.0), (50.0, 20e6), (1000.0, 125e3)] {
+            let power = thermal_noise_power(temperature, bandwidth, PHYSICS);
+            assert!((thermal_noise_temperature(power, bandwidth, PHYSICS) - temperature).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn textbook_constants() {
+        let textbook = Physics { constants: Constants::Textbook, ..PHYSICS };
+        let shift = noise_floor_with_nf(290.0, 1e6, 0.0, textbook) - noise_floor_with_nf(290.0, 1e6, 0.0, PHYSICS);
+        assert!((shift + 0.002).abs() < 0.001, "{shift}");
+        assert_eq!(lambda(3e8, textbook), 1.0);
+    }
+
+    #[test]
+    fn friis_round_trips() {
+        for frequency in [433e6, 2.4e9, 5.8e9, 28e9] {
+            for distance in [1.0, 10.0, 499.0, 500.0, 501.0, 2e3, 50e3] {
+                let loss = friis::path_loss(distance, 500.0, frequency, 3.5, friis::DEFAULT_REFERENCE_LOSS);
+                let inverse = friis::distance(loss, 500.0, frequency, 3.5, friis::DEFAULT_REFERENCE_LOSS);
+                assert!((inverse - distance).abs() < 1e-9 * distance, "{frequency} {distance}");
+            }
+        }
+        // free space below the break, 1 km at 2.4 GHz
+        let free_space = friis::path_loss(1e3, 1e6, 2.4e9, 2.0, friis::free_space_reference(PHYSICS));
+        assert!((free_space - 100.05).abs() < 0.01);
+    }
+
+    #[test]
+    fn db_sum_large_and_small() {
+        let sum = db_add(100.0, -174.0);
+        assert!(sum.is_finite());
+        assert!((sum - 100.0).abs() < 1e-12);
+
+        // would overflow as 10^(x / 10)
+        assert!((db_add(4000.0, 4000.0) - 4000.0 - 10.0 * f64::log10(2.0)).abs() < 1e-9);
+        assert!((db_sum([0.0, 0.0, 0.0, 0.0]) - 10.0 * f64::log10(4.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn friis_break_is_clamped() {
+        let reference = friis::DEFAULT_REFERENCE_LOSS;
+        let loss = friis::path_loss(100.0, 0.0, 1e9, -1.0, reference);
+        assert!(loss.is_finite());
+        assert_eq!(loss, friis::path_loss(100.0, friis::MIN_D_BREAK, 1e9, friis::MIN_BREAK_EXPONENT, reference));
+        assert!(friis::path_loss(200.0, 10.0, 1e9, 0.5, reference) > friis::path_loss(100.0, 10.0, 1e9, 0.5, reference));
+        let distance = friis::distance(loss, 0.0, 1e9, -1.0, reference);
+        assert!((distance - 100.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn friis_distance_stays_in_the_far_field() {
+        let reference = friis::DEFAULT_REFERENCE_LOSS;
+        let at_one_meter = friis::path_loss(1.0, 500.0, 1e9, 3.0, reference);
+        assert_eq!(friis::distance(at_one_meter - 20.0, 500.0, 1e9, 3.0, reference), friis::MIN_DISTANCE);
+        assert_eq!(friis::distance(at_one_meter - 20.0, 0.1, 1e9, 3.0, reference), friis::MIN_DISTANCE);
+        assert!(friis::distance(f64::NAN, 500.0, 1e9, 3.0, reference).is_nan());
+    }
+
+    #[test]
+    fn friis_inverse_across_the_break() {
+        let (d_break, exponent) = (500.0, 4.3);
+        let mut distance = 400.0;
+        while distance < 600.0 {
+            let loss = friis::path_loss(distance, d_break, 2.4e9, exponent, friis::DEFAULT_REFERENCE_LOSS);
+            let inverse = friis::distance(loss, d_break, 2.4e9, exponent, friis::DEFAULT_REFERENCE_LOSS);
+            assert!((inverse - distance).abs() < 1e-6 * distance, "{distance}");
+            distance += 0.5;
+        }
+        let below = friis::path_loss(d_break * (1.0 - 1e-9), d_break, 2.4e9, exponent, 32.44);
+        let above = friis::path_loss(d_break * (1.0 + 1e-9), d_break, 2.4e9, exponent, 32.44);
+        assert!((above - below).abs() < 1e-6);
+    }
+
+    #[test]
+    fn friis_reference_loss() {
+        assert!((friis::free_space_reference(PHYSICS) - friis::DEFAULT_REFERENCE_LOSS).abs() < 0.01);
+        let loss = friis::path_loss(1000.0, 500.0, 2.4e9, 3.0, 32.0);
+        assert!((friis::path_loss(1000.0, 500.0, 2.4e9, 3.0, 33.0) - loss - 1.0).abs() < 1e-12);
+        assert!((friis::distance(loss, 500.0, 2.4e9, 3.0, 32.0) - 1000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn slant_range_of_a_pass() {
+        assert!((geometry::slant_range(550.0, 90.0) - 550.0).abs() < 1e-9);
+        assert!((geometry::slant_range(550.0, 10.0) - 1815.1).abs() < 0.1);
+        assert!((geometry::slant_range(35786.0, 0.0) - 41672.8).abs() < 0.1);
+    }
+
+    #[test]
+    fn airmass_towards_the_horizon() {
+        assert!((geometry::airmass(90.0) - 1.0).abs() < 1e-3);
+        assert!((geometry::airmass(30.0) - 2.0).abs() < 0.01);
+        let horizon = geometry::airmass(0.0);
+        assert!(horizon.is_finite() && horizon > 30.0);
+        assert!(atmospheric::zenith_gas_loss(60e9, 288.15, 1013.25, 7.5) > 50.0);
+        assert!(atmospheric::zenith_gas_loss(12e9, 288.15, 1013.25, 7.5) < 0.5);
+    }
+
+    #[test]
+    fn two_ray_inverse() {
+        let (tx_height, rx_height, frequency) = (10.0, 2.0, 900e6);
+        for distance in [50.0, 300.0, 5e3, 20e3] {
+            let loss = two_ray::path_loss(distance, tx_height, rx_height, frequency, PHYSICS);
+            let inverse = two_ray::distance(loss, tx_height, rx_height, frequency, PHYSICS);
+            assert!(inverse >= distance * (1.0 - 1e-6));
+            let inverse_loss = two_ray::path_loss(inverse, tx_height, rx_height, frequency, PHYSICS);
+            assert!((inverse_loss - loss).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn gas_loss_reference_values() {
+        let specific = |f: f64| atmospheric::gas_loss(f * 1e9, 1000.0, 288.15, 1013.0, 7.5);
+        // oxygen peak around 60 GHz is about 15 dB/km, the water line at 22 GHz about 0.2 dB/km
+        assert!((specific(60.0) - 15.0).abs() < 1.0);
+        assert!(specific(22.235) > 0.15 && specific(22.235) < 0.3);
+        assert!(specific(10.0) < 0.02);
+        assert!(specific(183.31) > 20.0);
+    }
+
+    #[test]
+    fn rain_specific_attenuation() {
+        use rain::Polarization;
+        // table entries are reproduced exactly
+        let gamma = rain::specific_attenuation(20e9, 1.0, Polarization::Horizontal);
+        assert!((gamma - 0.0751).abs() < 1e-9);
+        // 50 mm/h at 12 GHz is roughly 2.1 dB/km
+        let gamma = rain::specific_attenuation(12e9, 50.0, Polarization::Vertical);
+        assert!(gamma > 1.8 && gamma < 2.4);
+        let h = rain::specific_attenuation(14e9, 25.0, Polarization::Horizontal);
+        let v = rain::specific_attenuation(14e9, 25.0, Polarization::Vertical);
+        let c = rain::specific_attenuation(14e9, 25.0, Polarization::Circular);
+        assert!(v < c && c < h);
+        assert_eq!(rain::specific_attenuation(14e9, 0.0, Polarization::Horizontal), 0.0);
+    }
+
+    #[test]
+    fn ebn0_snr_conversion() {
+        // 2 bit/s/Hz adds 3 dB
+        assert!((ebn0_to_snr(10.0, 40e6, 20e6) - 13.0103).abs() < 1e-4);
+        assert!((snr_to_ebn0(ebn0_to_snr(7.5, 1e6, 20e6), 1e6, 20e6) - 7.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn parabolic_gain_reference() {
+        // a 1.2 m dish at 12 GHz with 55 % efficiency is about 41.0 dBi
+        let gain = antenna::parabolic_gain(1.2, 12e9, antenna::DEFAULT_EFFICIENCY, PHYSICS);
+        assert!((gain - 41.0).abs() < 0.1, "{gain}");
+    }
+
+    #[test]
+    fn polarization_loss_stays_finite() {
+        assert!(polarization_loss(0.0).abs() < 1e-12);
+        assert!((polarization_loss(45.0) - 3.0103).abs() < 1e-4);
+        assert_eq!(polarization_loss(90.0), MAX_POLARIZATION_LOSS);
+        assert_eq!(polarization_loss(-90.0), MAX_POLARIZATION_LOSS);
+    }
+
+    #[test]
+    fn fading_margins() {
+        // 99.9 % availability over Rayleigh fading needs 30 dB
+        assert!((fading::rayleigh_margin(0.999) - 30.0).abs() < 0.01);
+        assert!((fading::rician_margin(0.999, 0.0) - fading::rayleigh_margin(0.999)).abs() < 0.01);
+        let strong_los = fading::rician_margin(0.999, 10.0);
+        assert!(strong_los > 5.0 && strong_los < fading::rician_margin(0.999, 1.0));
+        assert_eq!(fading::rayleigh_margin(1.0), fading::MAX_MARGIN);
+    }
+
+    #[test]
+    fn monte_carlo_statistics() {
+        let distributions = [Normal { mean: 5.0, std_dev: 3.0 }, Normal { mean: -2.0, std_dev: 4.0 }];
+        let samples = monte_carlo(100_000, &distributions);
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / samples.len() as f64;
+        assert!((mean - 3.0).abs() < 0.05, "{mean}");
+        assert!((variance.sqrt() - 5.0).abs() < 0.05);
+        // 5th percentile of a normal distribution is 1.645 standard deviations below the mean
+        assert!((percentile(&samples, 5.0) - (3.0 - 1.645 * 5.0)).abs() < 0.1);
+        assert!(percentile(&[], 5.0).is_nan());
+    }
+
+    #[test]
+    fn fresnel_radius_midpath() {
+        // 10 km hop at 6 GHz, the first zone is about 11.2 m wide at midpath
+        let radius = fresnel_radius(1, 5e3, 5e3, 6e9, PHYSICS);
+        assert!((radius - 11.18).abs() < 0.01, "{radius}");
+        assert!((fresnel_radius(4, 5e3, 5e3, 6e9, PHYSICS) - 2.0 * radius).abs() < 1e-9);
+    }
+
+    #[test]
+    fn knife_edge_loss() {
+        // grazing incidence loses half the field, 6 dB
+        assert!((diffraction::knife_edge(0.0) - 6.0).abs() < 0.1);
+        assert_eq!(diffraction::knife_edge(-1.0), 0.0);
+        assert!((diffraction::knife_edge(2.4) - 20.6).abs() < 0.1);
+        // the first Fresnel zone radius is v = sqrt(2)
+        let radius = fresnel_radius(1, 3e3, 7e3, 6e9, PHYSICS);
+        assert!((diffraction::parameter(radius, 3e3, 7e3, 6e9, PHYSICS) - f64::sqrt(2.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn voltage_and_field_strength() {
+        // 0 dBm into 50 Ohm is 107 dBuV
+        assert!((dbm_to_dbuv(0.0, SYSTEM_IMPEDANCE) - 106.99).abs() < 0.01);
+        assert!((dbuv_to_dbm(dbm_to_dbuv(-60.0, 75.0), 75.0) + 60.0).abs() < 1e-12);
+        // 1 W EIRP gives 5.48 V/m at 1 m
+        assert!((dbm_to_field_strength(30.0, 0.0, 1.0) - 5.477).abs() < 1e-3);
+        let field_strength = dbm_to_field_strength(20.0, 6.0, 300.0);
+        assert!((field_strength_to_dbm(field_strength, 6.0, 300.0) - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn temperature_conversion() {
+        assert!((celsius_to_kelvin(25.0) - 298.15).abs() < 1e-9);
+        assert!((kelvin_to_fahrenheit(celsius_to_kelvin(100.0)) - 212.0).abs() < 1e-9);
+        assert!((fahrenheit_to_kelvin(-40.0) - celsius_to_kelvin(-40.0)).abs() < 1e-9);
+        assert!((kelvin_to_celsius(fahrenheit_to_kelvin(77.0)) - 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn pattern_interpolation() {
+        let full_circle = [(0.0, 10.0), (90.0, 0.0), (180.0, -10.0), (270.0, 0.0)];
+        assert_eq!(antenna::interpolate_gain(&full_circle, 45.0), 5.0);
+        assert_eq!(antenna::interpolate_gain(&full_circle, -45.0), 5.0);
+        assert_eq!(antenna::interpolate_gain(&full_circle, 315.0), 5.0);
+        assert_eq!(antenna::interpolate_gain(&full_circle, 720.0 + 90.0), 0.0);
+
+        let sector = [(-30.0, 0.0), (0.0, 15.0), (30.0, 0.0)];
+        assert_eq!(antenna::interpolate_gain(&sector, 15.0), 7.5);
+        assert_eq!(antenna::interpolate_gain(&sector, 60.0), 0.0);
+        assert_eq!(antenna::interpolate_gain(&sector, 330.0), 0.0);
+        assert_eq!(antenna::interpolate_gain(&sector, 345.0), 7.5);
+        assert_eq!(antenna::interpolate_gain(&[(10.0, 3.0)], 200.0), 3.0);
+        assert!(antenna::interpolate_gain(&[], 0.0).is_nan());
+    }
+
+    #[test]
+    fn beamwidth_and_gain() {
+        let gain = antenna::gain_from_beamwidth(30.0, 30.0);
+        assert!((gain - 14.77).abs() < 0.01);
+        assert!((antenna::beamwidth_from_gain(gain) - 30.0).abs() < 1e-9);
+        assert!(antenna::gain_from_beamwidth(0.0, 10.0).is_nan());
+    }
+
+    #[test]
+    fn propagation_delay_of_a_hop() {
+        assert!((propagation_delay(299792458.0, PHYSICS) - 1.0).abs() < 1e-12);
+        assert!((propagation_delay(30e3, PHYSICS) - 100.07e-6).abs() < 0.01e-6);
+    }
+
+    #[test]
+    fn refractive_index_slows_the_wave() {
+        let water = Physics { refractive_index: 1.33, ..PHYSICS };
+        assert!((1.33 * lambda(1e9, water) - lambda(1e9, PHYSICS)).abs() < 1e-12);
+        assert!((propagation_delay(1e3, water) - 1.33 * propagation_delay(1e3, PHYSICS)).abs() < 1e-15);
+        let below_vacuum = Physics { refractive_index: 0.5, ..PHYSICS };
+        assert_eq!(lambda(1e9, below_vacuum), lambda(1e9, PHYSICS));
+    }
+
+    #[test]
+    fn cold_sky_lowers_the_noise_floor() {
+        let receiver = noise_temperature(1.0);
+        let cold = watt_to_dbm(thermal_noise_power(system_noise_temperature(30.0, receiver), 1e6, PHYSICS));
+        let warm = watt_to_dbm(thermal_noise_power(system_noise_temperature(290.0, receiver), 1e6, PHYSICS));
+        assert!((warm - noise_floor_with_nf(290.0, 1e6, 1.0, PHYSICS)).abs() < 1e-9);
+        assert!(warm - cold > 5.0);
+    }
+
+    #[test]
+    fn noise_cascade_first_stage_dominates() {
+        assert_eq!(noise::cascade(&[]), 0.0);
+        assert!((noise::cascade(&[(20.0, 1.5)]) - 1.5).abs() < 1e-9);
+        let lna_first = noise::cascade(&[(20.0, 1.0), (-3.0, 3.0), (-7.0, 10.0)]);
+        let cable_first = noise::cascade(&[(-3.0, 3.0), (20.0, 1.0), (-7.0, 10.0)]);
+        assert!((lna_first - 1.61).abs() < 0.01);
+        assert!((cable_first - 4.30).abs() < 0.01);
+    }
+
+    #[test]
+    fn feeder_loss() {
+        use feeder::FeederType;
+        assert!((feeder::loss(FeederType::Rg58, 100.0, 1e9) - 66.0).abs() < 1.0);
+        assert!((feeder::loss(FeederType::Lmr400, 100.0, 900e6) - 12.8).abs() < 0.5);
+        assert!((feeder::loss(FeederType::Wr90, 1.0, 10e9) - 0.108).abs() < 0.005);
+        assert!((FeederType::Wr90.cutoff_frequency() - 6.557e9).abs() < 1e7);
+        assert!(feeder::loss(FeederType::Wr90, 1.0, 5e9).is_infinite());
+        assert!(feeder::loss(FeederType::Wc109, 1.0, 10e9) > 0.0);
+        assert!(feeder::loss(FeederType::Rg58, 1.0, 0.0).is_nan());
+    }
+
+    #[test]
+    fn relay_snr_of_equal_hops() {
+        assert!((relay_snr(&[20.0]) - 20.0).abs() < 1e-9);
+        assert!((relay_snr(&[20.0, 20.0]) - (20.0 - 10.0 * f64::log10(2.0))).abs() < 1e-9);
+        assert!((relay_snr(&[10.0, 40.0]) - 10.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn shannon_capacity_low_snr() {
+        let snr_db = -30.0;
+        let snr = f64::powf(10.0, snr_db / 10.0);
+        let naive = f64::log2(1.0 + snr);
+        let stable = shannon_capacity(1.0, snr_db);
+
+        // for small x, log2(1 + x) = (x - x^2 / 2 + x^3 / 3) / ln 2
+        let expected = (snr - snr * snr / 2.0 + snr * snr * snr / 3.0) / std::f64::consts::LN_2;
+        assert!((stable - expected).abs() / expected < 1e-9);
+        assert!((naive - stable).abs() / expected < 1e-6);
+    }
+
+    #[test]
+    fn shannon_required_snr_inverts_capacity() {
+        for snr_db in [-30.0, -10.0, 0.0, 10.0, 30.0] {
+            let capacity = shannon_capacity(20e6, snr_db);
+            assert!((shannon_required_snr(capacity, 20e6) - snr_db).abs() < 1e-9);
+        }
     }
 }
@@ -0,0 +1,101 @@
+use link_budget_calculator::budget::{Gain, Loss};
+use link_budget_calculator::{parse_prefixed, LinkBudget, Target};
+
+pub const USAGE: &str = "\
+usage: link-budget-calculator --cli [options]
+
+  --json FILE            read the budget from a JSON file, options given after it override it
+  --frequency HZ         carrier frequency, suffixes like 2.4G are accepted
+  --bandwidth HZ         receiver bandwidth
+  --distance M           link distance in meter, suffixes like 10k are accepted
+  --tx-power DBM         transmit power
+  --temperature K        noise temperature
+  --noise-figure DB      receiver noise figure
+  --snr DB               target SNR when solving for the distance, the Tx power or the bandwidth
+  --required-snr DB      SNR the receiver needs, the margin is relative to it
+  --gain NAME=DB         add a gain, can be repeated
+  --loss NAME=DB         add a loss, can be repeated
+  --solve TARGET         snr (default), distance, tx-power or bandwidth
+
+A JSON budget holds every input of the budget the user interface solves, missing fields take
+the default values.";
+
+/// Headless mode, parse the arguments, solve the budget and print it to stdout
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut budget = LinkBudget::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            println!("{USAGE}");
+            return Ok(());
+        }
+        let value = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+        match arg.as_str() {
+            "--json" => {
+                let json = std::fs::read_to_string(value).map_err(|e| format!("failed to read {value}: {e}"))?;
+                budget = serde_json::from_str(&json).map_err(|e| format!("{value} is not a valid budget: {e}"))?;
+            }
+            "--frequency" => { budget.frequency = number(arg, value)? }
+            "--bandwidth" => { budget.bandwidth = number(arg, value)? }
+            "--distance" => { budget.distance = number(arg, value)? }
+            "--tx-power" => { budget.tx_power = number(arg, value)? }
+            "--temperature" => { budget.temperature = number(arg, value)? }
+            "--noise-figure" => { budget.noise_figure = number(arg, value)? }
+            "--snr" => { budget.snr = number(arg, value)? }
+            "--required-snr" => { budget.required_snr = number(arg, value)? }
+            "--gain" => {
+                let (name, gain) = named_value(arg, value)?;
+                budget.gains.insert(name, Gain::new(gain));
+            }
+            "--loss" => {
+                let (name, loss) = named_value(arg, value)?;
+                budget.losses.insert(name, Loss::new(loss));
+            }
+            "--solve" => {
+                budget.target = match value.as_str() {
+                    "snr" => Target::Snr,
+                    "distance" => Target::Distance,
+                    "tx-power" => Target::TxPower,
+                    "bandwidth" => Target::Bandwidth,
+                    _ => return Err(format!("unknown target {value}")),
+                };
+            }
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+
+    let solution = budget.solve();
+    if !solution.is_finite() {
+        return Err("the budget has no finite solution".to_string());
+    }
+    print_budget(&budget);
+    Ok(())
+}
+
+fn number(arg: &str, value: &str) -> Result<f64, String> {
+    parse_prefixed(value).ok_or_else(|| format!("{arg}: {value} is not a number"))
+}
+
+/// A NAME=DB pair
+fn named_value(arg: &str, value: &str) -> Result<(String, f64), String> {
+    let (name, db) = value.rsplit_once('=').ok_or_else(|| format!("{arg}: expected NAME=DB, got {value}"))?;
+    Ok((name.to_string(), number(arg, db)?))
+}
+
+fn print_budget(budget: &LinkBudget) {
+    println!("frequency     {:>10.4e} Hz", budget.frequency);
+    println!("bandwidth     {:>10.4e} Hz", budget.bandwidth);
+    println!("distance      {:>10.1} m", budget.distance);
+    println!("tx power      {:>10.2} dBm", budget.tx_power);
+    // signed gains and losses, they sum up to the rx power
+    let terms = budget.breakdown();
+    for term in &terms[1..] {
+        println!("              {:>10.2} dB  {}", term.value, term.name);
+    }
+    let rx_power: f64 = terms.iter().map(|term| term.value).sum();
+    println!("rx power      {rx_power:>10.2} dBm");
+    println!("noise floor   {:>10.2} dBm", budget.noise_floor());
+    println!("snr           {:>10.2} dB", budget.snr);
+    println!("margin        {:>10.2} dB", budget.link_margin());
+}
@@ -0,0 +1,28 @@
+/// Render a series as CSV, fields containing separators or quotes are quoted
+pub fn series_to_csv(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut csv = csv_line(headers.iter().map(|h| h.to_string()));
+    for row in rows {
+        csv.push_str(&csv_line(row.iter().cloned()));
+    }
+    csv
+}
+
+fn csv_line(fields: impl Iterator<Item = String>) -> String {
+    let fields: Vec<String> = fields.map(|field| csv_field(&field)).collect();
+    fields.join(",") + "\n"
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Write a screenshot as PNG file
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_png(path: &std::path::Path, screenshot: &egui::ColorImage) -> Result<(), image::ImageError> {
+    let [width, height] = screenshot.size;
+    image::save_buffer(path, screenshot.as_raw(), width as u32, height as u32, image::ColorType::Rgba8)
+}
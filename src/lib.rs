@@ -0,0 +1,80 @@
+//! Link budget calculations for noise limited wireless links.
+//!
+//! [`calc`] holds the building blocks: thermal noise, power and length conversions and the
+//! propagation models. [`LinkBudget`] combines them into the budget the user interface shows, it
+//! is solved the same way without a user interface:
+//!
+//! ```
+//! use link_budget_calculator::{LinkBudget, Target};
+//! use link_budget_calculator::budget::Gain;
+//!
+//! let mut budget = LinkBudget {
+//!     target: Target::Snr,
+//!     gains: [("Tx antenna", 12.0), ("Rx antenna", 12.0)]
+//!         .into_iter()
+//!         .map(|(name, gain)| (name.to_string(), Gain::new(gain)))
+//!         .collect(),
+//!     ..LinkBudget::default()
+//! };
+//! let snr = budget.solve();
+//! assert!(snr.is_finite());
+//! ```
+
+pub mod budget;
+pub mod calc;
+
+use number_prefix::{NumberPrefix, Prefix};
+
+pub use budget::{LinkBudget, Target};
+
+/// Parse a number with an optional SI suffix like 20M or 1.5k
+pub fn parse_prefixed(value: &str) -> Option<f64> {
+    let Ok(number_prefix) = value.trim().parse::<NumberPrefix<f64>>() else {
+        return None;
+    };
+
+    return match number_prefix {
+        NumberPrefix::Standalone(number) => Some(number),
+        NumberPrefix::Prefixed(prefix, number) => {
+            let factor = match prefix {
+                Prefix::Kilo => 1e3,
+                Prefix::Mega => 1e6,
+                Prefix::Giga => 1e9,
+                Prefix::Tera => 1e12,
+                Prefix::Peta => 1e15,
+                Prefix::Exa => 1e18,
+                Prefix::Zetta => 1e21,
+                Prefix::Yotta => 1e24,
+                _ => return None,
+            };
+
+            Some(factor * number)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use budget::Loss;
+
+    #[test]
+    fn solutions_balance_the_budget() {
+        for target in Target::ALL {
+            let mut budget = LinkBudget {
+                target,
+                ..LinkBudget::default()
+            };
+            budget.losses.insert("cable".to_string(), Loss::new(3.0));
+            budget.solve();
+            assert!(budget.total_sum().abs() < 1e-9, "{target:?}");
+        }
+    }
+
+    #[test]
+    fn prefixed_numbers() {
+        assert_eq!(parse_prefixed("20M"), Some(20e6));
+        assert_eq!(parse_prefixed(" 1.5k"), Some(1500.0));
+        assert_eq!(parse_prefixed("x"), None);
+    }
+}
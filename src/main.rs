@@ -1,13 +1,32 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 use egui::{Vec2, ViewportBuilder};
-mod calc;
 mod app;
+mod asynch;
+mod export;
+#[cfg(not(target_arch = "wasm32"))]
+mod cli;
 
 #[cfg(not(target_arch = "wasm32"))]
 fn main() {
-    let viewport_builder = ViewportBuilder::default().with_inner_size(Vec2::new(500.0, 600.0));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--cli") {
+        let args: Vec<String> = args.into_iter().filter(|arg| arg != "--cli").collect();
+        if let Err(e) = cli::run(&args) {
+            eprintln!("error: {e}");
+            eprintln!("{}", cli::USAGE);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // the size is only the initial one, eframe restores the last window size and position from
+    // storage and clamps it to the available monitors
+    let viewport_builder = ViewportBuilder::default()
+        .with_inner_size(Vec2::new(500.0, 600.0))
+        .with_min_inner_size(Vec2::new(300.0, 200.0));
     let native_options = eframe::NativeOptions {
         viewport: viewport_builder,
+        persist_window: true,
         ..eframe::NativeOptions::default()
     };
     eframe::run_native(